@@ -1,41 +1,115 @@
-use aoc2022::{get_day_input, print_elapsed_time};
-use std::char::ParseCharError;
+use aoc2022::{get_day_input, print_elapsed_time, Rng};
+use std::cmp::Reverse;
+use std::env;
+use std::fmt;
 use std::str::FromStr;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum Action {
     Rock = 1,
     Paper = 2,
     Scissors = 3,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum Outcome {
     Lose = 0,
     Draw = 3,
     Win = 6,
 }
 
+/// The raw second column of the strategy guide, before deciding what it means.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Column {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Round {
+    opponent: Action,
+    column: Column,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum ParseRoundError {
+    InvalidAction(String),
+    InvalidColumn(String),
+    WrongFieldCount(usize),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct ParseGuideError {
+    line: usize,
+    error: ParseRoundError,
+}
+
+impl fmt::Display for ParseGuideError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: ", self.line)?;
+        match &self.error {
+            ParseRoundError::InvalidAction(s) => write!(f, "expected A, B or C but got {:?}", s),
+            ParseRoundError::InvalidColumn(s) => write!(f, "expected X, Y or Z but got {:?}", s),
+            ParseRoundError::WrongFieldCount(n) => write!(f, "expected 2 fields but got {}", n),
+        }
+    }
+}
+
 impl FromStr for Action {
-    type Err = ParseCharError;
+    type Err = ParseRoundError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "A" | "X" => Ok(Action::Rock),
-            "B" | "Y" => Ok(Action::Paper),
-            "C" | "Z" => Ok(Action::Scissors),
-            _ => panic!("Didn't match an action"),
+            "A" => Ok(Action::Rock),
+            "B" => Ok(Action::Paper),
+            "C" => Ok(Action::Scissors),
+            _ => Err(ParseRoundError::InvalidAction(String::from(s))),
         }
     }
 }
 
-impl FromStr for Outcome {
-    type Err = ParseCharError;
+impl FromStr for Column {
+    type Err = ParseRoundError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "A" | "X" => Ok(Outcome::Lose),
-            "B" | "Y" => Ok(Outcome::Draw),
-            "C" | "Z" => Ok(Outcome::Win),
-            _ => panic!("Didn't match an action"),
+            "X" => Ok(Column::X),
+            "Y" => Ok(Column::Y),
+            "Z" => Ok(Column::Z),
+            _ => Err(ParseRoundError::InvalidColumn(String::from(s))),
+        }
+    }
+}
+
+impl FromStr for Round {
+    type Err = ParseRoundError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let [opponent, column] = s.split_whitespace().collect::<Vec<_>>()[..] {
+            Ok(Round {
+                opponent: opponent.parse()?,
+                column: column.parse()?,
+            })
+        } else {
+            Err(ParseRoundError::WrongFieldCount(
+                s.split_whitespace().count(),
+            ))
+        }
+    }
+}
+
+impl Column {
+    fn as_action(&self) -> Action {
+        match self {
+            Column::X => Action::Rock,
+            Column::Y => Action::Paper,
+            Column::Z => Action::Scissors,
+        }
+    }
+
+    fn as_outcome(&self) -> Outcome {
+        match self {
+            Column::X => Outcome::Lose,
+            Column::Y => Outcome::Draw,
+            Column::Z => Outcome::Win,
         }
     }
 }
@@ -70,51 +144,319 @@ impl Outcome {
     }
 }
 
-fn part_one(input: &[(Action, Action)]) -> u32 {
-    input.iter().fold(0, |acc, (opponent, me)| -> u32 {
-        acc + me.play(opponent) as u32 + *me as u32
-    })
+/// Scores for every round, indexed by the opponent's action then the column.
+type ScoreTable = [[u32; 3]; 3];
+
+fn score_table<F>(score: F) -> ScoreTable
+where
+    F: Fn(Action, Column) -> u32,
+{
+    let mut table = [[0; 3]; 3];
+    for opponent in [Action::Rock, Action::Paper, Action::Scissors] {
+        for column in [Column::X, Column::Y, Column::Z] {
+            table[opponent as usize - 1][column as usize] = score(opponent, column);
+        }
+    }
+    table
 }
 
-fn part_two(input: &[(Action, Outcome)]) -> u32 {
-    input.iter().fold(0, |acc, (opponent, outcome)| {
-        acc + outcome.needed_action(opponent) as u32 + *outcome as u32
-    })
+fn round_score(table: &ScoreTable, round: &Round) -> u32 {
+    table[round.opponent as usize - 1][round.column as usize]
 }
 
-fn parse_input_p1(input_str: &str) -> Vec<(Action, Action)> {
-    input_str
-        .lines()
-        .map(|line| {
-            let actions: Vec<Action> = line
-                .trim()
-                .split_whitespace()
-                .map(|action| action.parse())
-                .collect::<Result<_, _>>()
-                .unwrap();
-            (actions[0], actions[1])
+fn score_with_action(opponent: Action, me: Action) -> u32 {
+    me.play(&opponent) as u32 + me as u32
+}
+
+fn score_with_outcome(opponent: Action, outcome: Outcome) -> u32 {
+    outcome.needed_action(&opponent) as u32 + outcome as u32
+}
+
+fn score_guide(input: &[Round], table: &ScoreTable) -> u32 {
+    input.iter().map(|round| round_score(table, round)).sum()
+}
+
+fn part_one(input: &[Round]) -> u32 {
+    let table = score_table(|opponent, column| score_with_action(opponent, column.as_action()));
+    score_guide(input, &table)
+}
+
+fn part_two(input: &[Round]) -> u32 {
+    let table = score_table(|opponent, column| score_with_outcome(opponent, column.as_outcome()));
+    score_guide(input, &table)
+}
+
+/// An assignment of the second column's `X`, `Y` and `Z` to either moves or
+/// outcomes, in that order.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Mapping {
+    Actions([Action; 3]),
+    Outcomes([Outcome; 3]),
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = match self {
+            Mapping::Actions(actions) => actions.iter().map(|a| format!("{:?}", a)).collect(),
+            Mapping::Outcomes(outcomes) => outcomes.iter().map(|o| format!("{:?}", o)).collect(),
+        };
+        write!(f, "X={} Y={} Z={}", names[0], names[1], names[2])
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Interpretation {
+    mapping: Mapping,
+    total: u32,
+    round_scores: Vec<u32>,
+}
+
+fn permutations<T: Copy>(items: [T; 3]) -> Vec<[T; 3]> {
+    let [a, b, c] = items;
+    vec![
+        [a, b, c],
+        [a, c, b],
+        [b, a, c],
+        [b, c, a],
+        [c, a, b],
+        [c, b, a],
+    ]
+}
+
+/// Score the guide under every way of reading the second column, best first.
+fn explore_interpretations(input: &[Round]) -> Vec<Interpretation> {
+    let action_mappings = permutations([Action::Rock, Action::Paper, Action::Scissors])
+        .into_iter()
+        .map(Mapping::Actions);
+    let outcome_mappings = permutations([Outcome::Lose, Outcome::Draw, Outcome::Win])
+        .into_iter()
+        .map(Mapping::Outcomes);
+
+    let mut interpretations: Vec<Interpretation> = action_mappings
+        .chain(outcome_mappings)
+        .map(|mapping| {
+            let table = score_table(|opponent, column| match mapping {
+                Mapping::Actions(actions) => score_with_action(opponent, actions[column as usize]),
+                Mapping::Outcomes(outcomes) => {
+                    score_with_outcome(opponent, outcomes[column as usize])
+                }
+            });
+            let round_scores: Vec<u32> = input
+                .iter()
+                .map(|round| round_score(&table, round))
+                .collect();
+            Interpretation {
+                mapping,
+                total: round_scores.iter().sum(),
+                round_scores,
+            }
         })
-        .collect()
+        .collect();
+
+    interpretations.sort_by_key(|i| Reverse(i.total));
+    interpretations
 }
 
-fn parse_input_p2(input_str: &str) -> Vec<(Action, Outcome)> {
+fn print_interpretation(label: &str, interpretation: &Interpretation) {
+    println!(
+        "{}: {} scores {}",
+        label, interpretation.mapping, interpretation.total
+    );
+    for (i, score) in interpretation.round_scores.iter().enumerate() {
+        println!("  Round {}: {}", i + 1, score);
+    }
+}
+
+fn explore(input: &[Round]) {
+    let interpretations = explore_interpretations(input);
+    println!("Interpretations:");
+    for interpretation in &interpretations {
+        println!("  {}: {}", interpretation.mapping, interpretation.total);
+    }
+    print_interpretation("Best", interpretations.first().unwrap());
+    print_interpretation("Worst", interpretations.last().unwrap());
+}
+
+const ACTIONS: [Action; 3] = [Action::Rock, Action::Paper, Action::Scissors];
+
+/// A way of choosing actions over repeated rounds, along with whatever it has
+/// learned so far about its opponent.
+#[derive(Clone, PartialEq, Debug)]
+enum Strategy {
+    /// Cycle through the moves of a strategy guide, read as in part one,
+    /// which mustn't be empty.
+    Guide(Vec<Action>, usize),
+    Random,
+    /// Play whatever beats the opponent's most common action so far.
+    FrequencyCounter([u32; 3]),
+    /// Keep a winning action, otherwise move on to the one that beats it.
+    WinStayLoseShift(Option<(Action, Outcome)>),
+}
+
+impl Strategy {
+    fn name(&self) -> &'static str {
+        match self {
+            Strategy::Guide(..) => "Guide",
+            Strategy::Random => "Random",
+            Strategy::FrequencyCounter(_) => "Frequency counter",
+            Strategy::WinStayLoseShift(_) => "Win-stay lose-shift",
+        }
+    }
+
+    fn choose(&mut self, rng: &mut Rng) -> Action {
+        let random = |rng: &mut Rng| ACTIONS[rng.below(3) as usize];
+        match self {
+            Strategy::Guide(actions, next) => {
+                let action = actions[*next % actions.len()];
+                *next += 1;
+                action
+            }
+            Strategy::Random => random(rng),
+            Strategy::FrequencyCounter(counts) => {
+                if counts.iter().all(|c| *c == 0) {
+                    random(rng)
+                } else {
+                    let (most_common, _) = ACTIONS
+                        .iter()
+                        .zip(counts.iter())
+                        .max_by_key(|(_, count)| **count)
+                        .unwrap();
+                    Outcome::Win.needed_action(most_common)
+                }
+            }
+            Strategy::WinStayLoseShift(None) => random(rng),
+            Strategy::WinStayLoseShift(Some((action, Outcome::Win))) => *action,
+            Strategy::WinStayLoseShift(Some((action, _))) => Outcome::Win.needed_action(action),
+        }
+    }
+
+    fn observe(&mut self, mine: Action, theirs: Action) {
+        match self {
+            Strategy::FrequencyCounter(counts) => counts[theirs as usize - 1] += 1,
+            Strategy::WinStayLoseShift(last) => *last = Some((mine, mine.play(&theirs))),
+            Strategy::Guide(..) | Strategy::Random => {}
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+struct Standing {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    score: u32,
+}
+
+/// Play two strategies against each other, returning each one's total score.
+fn play_match(first: &Strategy, second: &Strategy, rounds: usize, rng: &mut Rng) -> (u32, u32) {
+    let mut first = first.clone();
+    let mut second = second.clone();
+    let mut scores = (0, 0);
+
+    for _ in 0..rounds {
+        let first_action = first.choose(rng);
+        let second_action = second.choose(rng);
+        scores.0 += score_with_action(second_action, first_action);
+        scores.1 += score_with_action(first_action, second_action);
+        first.observe(first_action, second_action);
+        second.observe(second_action, first_action);
+    }
+
+    scores
+}
+
+/// Play every strategy against every other one, returning the standings in
+/// the same order as the given strategies.
+fn round_robin(strategies: &[Strategy], rounds: usize, seed: u64) -> Vec<Standing> {
+    let mut rng = Rng::new(seed);
+    let mut standings = vec![Standing::default(); strategies.len()];
+
+    for i in 0..strategies.len() {
+        for j in i + 1..strategies.len() {
+            let (first, second) = play_match(&strategies[i], &strategies[j], rounds, &mut rng);
+            standings[i].score += first;
+            standings[j].score += second;
+            if first > second {
+                standings[i].wins += 1;
+                standings[j].losses += 1;
+            } else if first < second {
+                standings[i].losses += 1;
+                standings[j].wins += 1;
+            } else {
+                standings[i].draws += 1;
+                standings[j].draws += 1;
+            }
+        }
+    }
+
+    standings
+}
+
+fn tournament(input: &[Round], rounds: usize, seed: u64) {
+    let mut strategies = vec![
+        Strategy::Random,
+        Strategy::FrequencyCounter([0; 3]),
+        Strategy::WinStayLoseShift(None),
+    ];
+    // An empty guide has no moves to play.
+    if !input.is_empty() {
+        let guide = input.iter().map(|r| r.column.as_action()).collect();
+        strategies.insert(0, Strategy::Guide(guide, 0));
+    }
+    let standings = round_robin(&strategies, rounds, seed);
+
+    let mut table: Vec<_> = strategies.iter().zip(standings).collect();
+    table
+        .sort_by_key(|(_, standing)| Reverse((standing.wins * 2 + standing.draws, standing.score)));
+
+    println!("Tournament of {} rounds per match (seed {}):", rounds, seed);
+    println!(
+        "{:<20} {:>4} {:>4} {:>4} {:>10}",
+        "Strategy", "W", "D", "L", "Score"
+    );
+    for (strategy, standing) in table {
+        println!(
+            "{:<20} {:>4} {:>4} {:>4} {:>10}",
+            strategy.name(),
+            standing.wins,
+            standing.draws,
+            standing.losses,
+            standing.score
+        );
+    }
+}
+
+fn parse_input(input_str: &str) -> Result<Vec<Round>, ParseGuideError> {
     input_str
         .lines()
-        .map(|line| {
-            let inputs: Vec<&str> = line.trim().split_whitespace().collect();
-            (inputs[0].parse().unwrap(), inputs[1].parse().unwrap())
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse()
+                .map_err(|error| ParseGuideError { line: i + 1, error })
         })
         .collect()
 }
 
 fn main() {
     let input_str = get_day_input("02");
-    let input_p1 = parse_input_p1(&input_str);
-    let input_p2 = parse_input_p2(&input_str);
+    let input = parse_input(&input_str).unwrap_or_else(|e| panic!("{}", e));
     println!("Day 02:");
     println!("=========");
-    println!("Part one: {}", print_elapsed_time(|| part_one(&input_p1)));
-    println!("Part two: {}", print_elapsed_time(|| part_two(&input_p2)));
+    println!("Part one: {}", print_elapsed_time(|| part_one(&input)));
+    println!("Part two: {}", print_elapsed_time(|| part_two(&input)));
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("explore") => explore(&input),
+        Some("tournament") => {
+            let rounds = args
+                .get(1)
+                .map_or(1000, |r| r.parse().expect("Invalid rounds"));
+            let seed = args.get(2).map_or(0, |s| s.parse().expect("Invalid seed"));
+            tournament(&input, rounds, seed);
+        }
+        _ => {}
+    }
 }
 
 #[cfg(test)]
@@ -129,10 +471,87 @@ B X
 C Z",
         );
 
-        let input_p1 = parse_input_p1(&input_str);
-        let input_p2 = parse_input_p2(&input_str);
+        let input = parse_input(&input_str).unwrap();
+
+        assert_eq!(part_one(&input), 15);
+        assert_eq!(part_two(&input), 12);
+    }
+
+    #[test]
+    fn test_explore_interpretations_02() {
+        let input_str: String = String::from(
+            "A Y
+B X
+C Z",
+        );
+
+        let interpretations = explore_interpretations(&parse_input(&input_str).unwrap());
+
+        assert_eq!(interpretations.len(), 12);
+        let as_actions = interpretations
+            .iter()
+            .find(|i| {
+                i.mapping == Mapping::Actions([Action::Rock, Action::Paper, Action::Scissors])
+            })
+            .unwrap();
+        assert_eq!(as_actions.total, 15);
+        assert_eq!(as_actions.round_scores, vec![8, 1, 6]);
+        let as_outcomes = interpretations
+            .iter()
+            .find(|i| i.mapping == Mapping::Outcomes([Outcome::Lose, Outcome::Draw, Outcome::Win]))
+            .unwrap();
+        assert_eq!(as_outcomes.total, 12);
+        assert_eq!(interpretations.first().unwrap().total, 24);
+    }
+
+    #[test]
+    fn test_parse_errors_02() {
+        assert_eq!(
+            parse_input("A Y\nB X\nX Z"),
+            Err(ParseGuideError {
+                line: 3,
+                error: ParseRoundError::InvalidAction(String::from("X"))
+            })
+        );
+        assert_eq!(
+            parse_input("A Y\nB C"),
+            Err(ParseGuideError {
+                line: 2,
+                error: ParseRoundError::InvalidColumn(String::from("C"))
+            })
+        );
+        assert_eq!(
+            parse_input("A"),
+            Err(ParseGuideError {
+                line: 1,
+                error: ParseRoundError::WrongFieldCount(1)
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_robin_02() {
+        let strategies = vec![
+            Strategy::Guide(vec![Action::Rock], 0),
+            Strategy::Random,
+            Strategy::FrequencyCounter([0; 3]),
+            Strategy::WinStayLoseShift(None),
+        ];
+
+        let standings = round_robin(&strategies, 100, 2022);
 
-        assert_eq!(part_one(&input_p1), 15);
-        assert_eq!(part_two(&input_p2), 12);
+        assert_eq!(standings, round_robin(&strategies, 100, 2022));
+        assert_eq!(
+            standings
+                .iter()
+                .map(|s| s.wins + s.draws + s.losses)
+                .sum::<u32>(),
+            12
+        );
+        // Always playing rock is found out after the first round, after which
+        // the counter gains 7 points a round over it.
+        let mut rng = Rng::new(2022);
+        let (guide, counter) = play_match(&strategies[0], &strategies[2], 100, &mut rng);
+        assert!(counter >= guide + 7 * 99 - 4);
     }
 }
@@ -1,5 +1,7 @@
-use aoc2022::{get_day_input, print_elapsed_time};
+use aoc2022::{get_day_input, print_elapsed_time, Rng};
 use std::collections::HashSet;
+use std::env;
+use std::fmt;
 
 type Item = char;
 
@@ -13,6 +15,67 @@ fn item_priority(item: &Item) -> u32 {
     }
 }
 
+/// The inverse of `item_priority` for valid priorities.
+fn priority_item(priority: u32) -> Item {
+    match priority {
+        1..=26 => (b'a' + (priority - 1) as u8) as char,
+        27..=52 => (b'A' + (priority - 27) as u8) as char,
+        _ => panic!("No item has priority {}", priority),
+    }
+}
+
+/// A set of items stored as a bitmask, where each item's bit is one less than
+/// its priority.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    const ALL: ItemSet = ItemSet((1 << 52) - 1);
+
+    /// Add an item to the set, ignoring anything which isn't a valid item.
+    fn insert(&mut self, item: Item) {
+        let priority = item_priority(&item);
+        if priority > 0 {
+            self.0 |= 1 << (priority - 1);
+        }
+    }
+
+    fn intersection(&self, other: &ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn union(&self, other: &ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Item> {
+        let mut mask = self.0;
+        std::iter::from_fn(move || {
+            if mask == 0 {
+                None
+            } else {
+                let bit = mask.trailing_zeros();
+                mask &= mask - 1;
+                Some(priority_item(bit + 1))
+            }
+        })
+    }
+
+    fn priority_sum(&self) -> u32 {
+        self.iter().map(|item| item_priority(&item)).sum()
+    }
+}
+
+impl FromIterator<Item> for ItemSet {
+    fn from_iter<I: IntoIterator<Item = Item>>(iter: I) -> Self {
+        let mut set = ItemSet::default();
+        for item in iter {
+            set.insert(item);
+        }
+        set
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 struct Rucksack {
     first_compartment: Vec<Item>,
@@ -20,11 +83,16 @@ struct Rucksack {
 }
 
 impl Rucksack {
-    fn all_items(&self) -> Vec<Item> {
-        let mut all_items = Vec::new();
-        all_items.extend(&self.first_compartment);
-        all_items.extend(&self.second_compartment);
-        all_items
+    fn first_items(&self) -> ItemSet {
+        self.first_compartment.iter().cloned().collect()
+    }
+
+    fn second_items(&self) -> ItemSet {
+        self.second_compartment.iter().cloned().collect()
+    }
+
+    fn all_items(&self) -> ItemSet {
+        self.first_items().union(&self.second_items())
     }
 }
 
@@ -32,10 +100,91 @@ fn part_one(input: &[Rucksack]) -> u32 {
     input
         .iter()
         .map(|rucksack| {
-            let first_set: HashSet<Item> =
-                HashSet::from_iter(rucksack.first_compartment.iter().cloned());
-            let second_set: HashSet<Item> =
-                HashSet::from_iter(rucksack.second_compartment.iter().cloned());
+            rucksack
+                .first_items()
+                .intersection(&rucksack.second_items())
+                .priority_sum()
+        })
+        .sum()
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum GroupError {
+    Incomplete { group: usize, size: usize },
+    NoBadge { group: usize },
+    MultipleBadges { group: usize, badges: Vec<Item> },
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupError::Incomplete { group, size } => {
+                write!(f, "Group {} is incomplete with {} rucksacks", group, size)
+            }
+            GroupError::NoBadge { group } => write!(f, "Group {} has no common badge", group),
+            GroupError::MultipleBadges { group, badges } => write!(
+                f,
+                "Group {} has multiple common badges: {}",
+                group,
+                badges.iter().collect::<String>()
+            ),
+        }
+    }
+}
+
+/// Find the single item common to every rucksack in each group of
+/// `group_size` consecutive rucksacks, numbering groups from 1.
+fn group_badges(input: &[Rucksack], group_size: usize) -> Vec<Result<Item, GroupError>> {
+    assert!(group_size > 0, "Group size must be positive");
+    input
+        .chunks(group_size)
+        .enumerate()
+        .map(|(i, group)| {
+            let group_num = i + 1;
+            if group.len() < group_size {
+                return Err(GroupError::Incomplete {
+                    group: group_num,
+                    size: group.len(),
+                });
+            }
+            let common = group.iter().fold(ItemSet::ALL, |acc, rucksack| {
+                acc.intersection(&rucksack.all_items())
+            });
+            match common.iter().collect::<Vec<_>>()[..] {
+                [badge] => Ok(badge),
+                [] => Err(GroupError::NoBadge { group: group_num }),
+                ref badges => Err(GroupError::MultipleBadges {
+                    group: group_num,
+                    badges: badges.to_vec(),
+                }),
+            }
+        })
+        .collect()
+}
+
+fn part_two(input: &[Rucksack]) -> u32 {
+    group_badges(input, 3)
+        .into_iter()
+        .map(|badge| item_priority(&badge.unwrap_or_else(|e| panic!("{}", e))))
+        .sum()
+}
+
+fn print_badges(input: &[Rucksack], group_size: usize) {
+    for (i, badge) in group_badges(input, group_size).iter().enumerate() {
+        match badge {
+            Ok(badge) => println!("Group {}: {}", i + 1, badge),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+/// The original `HashSet` implementation of part one, kept for benchmarking.
+fn part_one_hashset(input: &[Rucksack]) -> u32 {
+    input
+        .iter()
+        .map(|rucksack| {
+            let first_set: HashSet<Item> = rucksack.first_compartment.iter().cloned().collect();
+            let second_set: HashSet<Item> = rucksack.second_compartment.iter().cloned().collect();
             first_set
                 .intersection(&second_set)
                 .map(item_priority)
@@ -44,20 +193,21 @@ fn part_one(input: &[Rucksack]) -> u32 {
         .sum()
 }
 
-fn part_two(input: &[Rucksack]) -> u32 {
+/// The original `HashSet` implementation of part two, kept for benchmarking.
+fn part_two_hashset(input: &[Rucksack]) -> u32 {
     input
         .chunks(3)
         .map(|group| {
             group
                 .iter()
-                .map(|rucksack| HashSet::from_iter(rucksack.all_items().to_owned()))
-                .fold(HashSet::new(), |acc, items| {
-                    if acc.is_empty() {
-                        items.to_owned()
-                    } else {
-                        acc.intersection(&items).cloned().collect()
-                    }
+                .map(|rucksack| {
+                    let mut all_items = Vec::new();
+                    all_items.extend(&rucksack.first_compartment);
+                    all_items.extend(&rucksack.second_compartment);
+                    HashSet::<Item>::from_iter(all_items)
                 })
+                .reduce(|acc, items| acc.intersection(&items).cloned().collect())
+                .unwrap_or_default()
                 .iter()
                 .map(item_priority)
                 .sum::<u32>()
@@ -65,11 +215,183 @@ fn part_two(input: &[Rucksack]) -> u32 {
         .sum()
 }
 
+fn shuffle<T>(items: &mut [T], rng: &mut Rng) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.below(i as u64 + 1) as usize);
+    }
+}
+
+/// Generate an input shaped like the puzzle's: every rucksack has exactly one
+/// item in both compartments, and every group of three exactly one badge.
+fn generate_input(groups: usize, rng: &mut Rng) -> String {
+    let mut lines = Vec::new();
+
+    for _ in 0..groups {
+        let mut items: Vec<Item> = (1..=52).map(priority_item).collect();
+        shuffle(&mut items, rng);
+        let badge = items[0];
+
+        // Each rucksack takes a shared item and 16 items of its own, so no
+        // other item can be common to the group.
+        for own in items[1..].chunks(17) {
+            let (shared, own) = (own[0], &own[1..]);
+            let (first_only, second_only) = own.split_at(own.len() / 2);
+            let len = 8 + rng.below(16) as usize;
+            let mut first: Vec<Item> = (0..len)
+                .map(|_| first_only[rng.below(first_only.len() as u64) as usize])
+                .collect();
+            let mut second: Vec<Item> = (0..len)
+                .map(|_| second_only[rng.below(second_only.len() as u64) as usize])
+                .collect();
+            first[0] = shared;
+            second[0] = shared;
+            first[1] = badge;
+            shuffle(&mut first, rng);
+            shuffle(&mut second, rng);
+            lines.push(first.into_iter().chain(second).collect::<String>());
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn bench(groups: usize) {
+    let input = parse_input(&generate_input(groups, &mut Rng::new(groups as u64)));
+    println!("Benchmarking {} rucksacks:", input.len());
+    println!(
+        "Part one (HashSet): {}",
+        print_elapsed_time(|| part_one_hashset(&input))
+    );
+    println!(
+        "Part one (ItemSet): {}",
+        print_elapsed_time(|| part_one(&input))
+    );
+    println!(
+        "Part two (HashSet): {}",
+        print_elapsed_time(|| part_two_hashset(&input))
+    );
+    println!(
+        "Part two (ItemSet): {}",
+        print_elapsed_time(|| part_two(&input))
+    );
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum RucksackError {
+    OddLength {
+        line: usize,
+        len: usize,
+    },
+    InvalidItem {
+        line: usize,
+        column: usize,
+        item: char,
+    },
+}
+
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RucksackError::OddLength { line, len } => {
+                write!(f, "Line {}: odd number of items ({})", line, len)
+            }
+            RucksackError::InvalidItem { line, column, item } => {
+                write!(
+                    f,
+                    "Line {}, column {}: invalid item {:?}",
+                    line, column, item
+                )
+            }
+        }
+    }
+}
+
+/// Find every line which can't be split evenly into compartments or which
+/// contains something other than an item.
+fn validate_input(input_str: &str) -> Vec<RucksackError> {
+    let mut errors = Vec::new();
+    for (i, line) in input_str.lines().enumerate() {
+        let len = line.chars().count();
+        if len % 2 != 0 {
+            errors.push(RucksackError::OddLength { line: i + 1, len });
+        }
+        for (j, item) in line.chars().enumerate() {
+            if item_priority(&item) == 0 {
+                errors.push(RucksackError::InvalidItem {
+                    line: i + 1,
+                    column: j + 1,
+                    item,
+                });
+            }
+        }
+    }
+    errors
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Compartment {
+    First,
+    Second,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Move {
+    item: Item,
+    count: usize,
+    from: Compartment,
+}
+
+impl Rucksack {
+    /// The fewest item moves between compartments leaving no item type in
+    /// both, which may leave the compartments different sizes.
+    ///
+    /// Each shared item type moves from whichever compartment has fewer of it,
+    /// or from the first if they have the same number.
+    fn repack(&self) -> Vec<Move> {
+        let count =
+            |compartment: &[Item], item: Item| compartment.iter().filter(|i| **i == item).count();
+        self.first_items()
+            .intersection(&self.second_items())
+            .iter()
+            .map(|item| {
+                let first = count(&self.first_compartment, item);
+                let second = count(&self.second_compartment, item);
+                if first <= second {
+                    Move {
+                        item,
+                        count: first,
+                        from: Compartment::First,
+                    }
+                } else {
+                    Move {
+                        item,
+                        count: second,
+                        from: Compartment::Second,
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+fn print_repacking(input: &[Rucksack]) {
+    for (i, rucksack) in input.iter().enumerate() {
+        let moves = rucksack.repack();
+        if !moves.is_empty() {
+            let moves: Vec<String> = moves
+                .iter()
+                .map(|m| format!("{} x{} from {:?}", m.item, m.count, m.from))
+                .collect();
+            println!("Rucksack {}: move {}", i + 1, moves.join(", "));
+        }
+    }
+}
+
 fn parse_input(input_str: &str) -> Vec<Rucksack> {
     input_str
         .lines()
         .map(|line| {
-            let first_compartment_end = line.len() / 2;
+            let first_compartment_end = line.chars().count() / 2;
             let mut first = Vec::new();
             first.extend(line.chars().take(first_compartment_end));
             let mut second = Vec::new();
@@ -87,6 +409,34 @@ fn main() {
     let input = parse_input(&input_str);
     println!("Day 03:");
     println!("=========");
+
+    // Modes run instead of the parts, as they may be reporting on rucksacks or
+    // groups the parts would panic on.
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("bench") => {
+            return bench(
+                args.get(1)
+                    .map_or(100_000, |g| g.parse().expect("Invalid group count")),
+            )
+        }
+        Some("badges") => {
+            return print_badges(
+                &input,
+                args.get(1)
+                    .map_or(3, |g| g.parse().expect("Invalid group size")),
+            )
+        }
+        Some("repack") => return print_repacking(&input),
+        Some("validate") => {
+            for error in validate_input(&input_str) {
+                println!("{}", error);
+            }
+            return;
+        }
+        _ => {}
+    }
+
     println!("Part one: {}", print_elapsed_time(|| part_one(&input)));
     println!("Part two: {}", print_elapsed_time(|| part_two(&input)));
 }
@@ -112,4 +462,101 @@ CrZsJsPPZsGzwwsLwLmpwMDw",
         assert_eq!(part_one(&input), 157);
         assert_eq!(part_two(&input), 70);
     }
+
+    #[test]
+    fn test_item_set_03() {
+        let first: ItemSet = "vJrwpWtwJgWr".chars().collect();
+        let second: ItemSet = "hcsFMMfFFhFp".chars().collect();
+
+        assert_eq!(
+            first.intersection(&second).iter().collect::<Vec<_>>(),
+            vec!['p']
+        );
+        assert_eq!(first.union(&second).iter().count(), 14);
+        assert_eq!("?!".chars().collect::<ItemSet>(), ItemSet::default());
+        assert_eq!(ItemSet::ALL.priority_sum(), (1..=52).sum());
+    }
+
+    #[test]
+    fn test_generated_input_03() {
+        let input = parse_input(&generate_input(100, &mut Rng::new(3)));
+
+        assert_eq!(input.len(), 300);
+        assert_eq!(part_one(&input), part_one_hashset(&input));
+        assert_eq!(part_two(&input), part_two_hashset(&input));
+    }
+
+    #[test]
+    fn test_group_badges_03() {
+        let input_str: String = String::from(
+            "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw",
+        );
+
+        let input = parse_input(&input_str);
+
+        assert_eq!(group_badges(&input, 3), vec![Ok('r'), Ok('Z')]);
+        assert_eq!(
+            group_badges(&input, 4),
+            vec![
+                Err(GroupError::NoBadge { group: 1 }),
+                Err(GroupError::Incomplete { group: 2, size: 2 })
+            ]
+        );
+        assert_eq!(
+            group_badges(&input[..2], 2),
+            vec![Err(GroupError::MultipleBadges {
+                group: 1,
+                badges: vec!['f', 'r', 's', 'F', 'M']
+            })]
+        );
+    }
+
+    #[test]
+    fn test_validate_input_03() {
+        assert_eq!(
+            validate_input("vJrwpWtwJgWrhcsFMMfFFhFp\nabc\nab1d"),
+            vec![
+                RucksackError::OddLength { line: 2, len: 3 },
+                RucksackError::InvalidItem {
+                    line: 3,
+                    column: 3,
+                    item: '1'
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repack_03() {
+        let input = parse_input("vJrwpWtwJgWrhcsFMMfFFhFp\naabcabBd");
+
+        assert_eq!(
+            input[0].repack(),
+            vec![Move {
+                item: 'p',
+                count: 1,
+                from: Compartment::First
+            }]
+        );
+        assert_eq!(
+            input[1].repack(),
+            vec![
+                Move {
+                    item: 'a',
+                    count: 1,
+                    from: Compartment::Second
+                },
+                Move {
+                    item: 'b',
+                    count: 1,
+                    from: Compartment::First
+                },
+            ]
+        );
+    }
 }
@@ -1,8 +1,10 @@
-use aoc2022::{get_day_input, print_elapsed_time, parse_input_lines};
-use std::{ops::RangeInclusive, num::ParseIntError, str::FromStr};
+use aoc2022::interval::{Interval, IntervalSet};
+use aoc2022::{get_day_input, parse_input_lines, print_elapsed_time};
+use std::cmp::Reverse;
+use std::{env, num::ParseIntError, str::FromStr};
 
 #[derive(Debug, Clone)]
-struct Range(RangeInclusive<u32>);
+struct Range(Interval);
 
 #[derive(Debug, Clone)]
 struct Assignment {
@@ -10,34 +12,257 @@ struct Assignment {
     second: Range,
 }
 
+#[derive(Debug, PartialEq)]
+enum ParseAssignmentError {
+    Malformed(String),
+    InvalidNumber(ParseIntError),
+    /// A range ending before it starts.
+    Reversed {
+        start: u64,
+        end: u64,
+    },
+}
+
+impl From<ParseIntError> for ParseAssignmentError {
+    fn from(e: ParseIntError) -> Self {
+        ParseAssignmentError::InvalidNumber(e)
+    }
+}
+
 impl FromStr for Range {
-    type Err = ParseIntError;
+    type Err = ParseAssignmentError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<_> = s.splitn(2, "-").map(|p| p.parse().unwrap()).collect();
-        Ok(Range(RangeInclusive::new(parts[0], parts[1])))
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| ParseAssignmentError::Malformed(String::from(s)))?;
+        let (start, end) = (start.parse()?, end.parse()?);
+        if start > end {
+            return Err(ParseAssignmentError::Reversed { start, end });
+        }
+        Ok(Range(Interval::new(start, end)))
     }
 }
 
 impl FromStr for Assignment {
-    type Err = ParseIntError;
+    type Err = ParseAssignmentError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<_> = s.splitn(2, ",").map(|p| p.parse::<Range>().unwrap()).collect();
-        Ok(Assignment { first: parts[0].clone(), second: parts[1].clone() })
+        let (first, second) = s
+            .split_once(',')
+            .ok_or_else(|| ParseAssignmentError::Malformed(String::from(s)))?;
+        Ok(Assignment {
+            first: first.parse()?,
+            second: second.parse()?,
+        })
     }
 }
 
 fn part_one(input: &[Assignment]) -> u32 {
-    input.iter().filter(|assignment| -> bool {
-        assignment.first.0.to_owned().into_iter().all(|i| assignment.second.0.contains(&i)) ||
-            assignment.second.0.to_owned().into_iter().all(|i| assignment.first.0.contains(&i))
-    }).count() as u32
+    input
+        .iter()
+        .filter(|assignment| -> bool {
+            assignment.first.0.contains(&assignment.second.0)
+                || assignment.second.0.contains(&assignment.first.0)
+        })
+        .count() as u32
 }
 
 fn part_two(input: &[Assignment]) -> u32 {
-    input.iter().filter(|assignment| -> bool {
-        assignment.first.0.to_owned().into_iter().any(|i| assignment.second.0.contains(&i)) ||
-            assignment.second.0.to_owned().into_iter().any(|i| assignment.first.0.contains(&i))
-    }).count() as u32
+    input
+        .iter()
+        .filter(|assignment| -> bool { assignment.first.0.overlaps(&assignment.second.0) })
+        .count() as u32
+}
+
+/// Every elf's range, where the elves of assignment `i` are `2 * i` and
+/// `2 * i + 1`.
+fn elves(input: &[Assignment]) -> Vec<&Range> {
+    input
+        .iter()
+        .flat_map(|assignment| [&assignment.first, &assignment.second])
+        .collect()
+}
+
+/// Name an elf from `elves` by its input line and which half of the line it
+/// is, such as `3:second`.
+fn elf_label(elf: usize) -> String {
+    format!("{}:{}", elf / 2 + 1, ["first", "second"][elf % 2])
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Coverage {
+    /// Sections between the lowest and highest assigned which nobody covers.
+    uncovered: IntervalSet,
+    /// The sections covered by the most elves, and how many elves that is.
+    busiest: (IntervalSet, usize),
+    distinct: u128,
+    /// The most elves which could be dropped without changing the coverage.
+    redundant: Vec<usize>,
+}
+
+/// Find the intervals covered by the greatest number of ranges, by sweeping
+/// over where each range starts and stops.
+fn busiest_sections(ranges: &[&Range]) -> (IntervalSet, usize) {
+    // Ranges may stop just past `u64::MAX`.
+    let mut events: Vec<(u128, i64)> = ranges
+        .iter()
+        .flat_map(|range| {
+            [
+                (range.0.start() as u128, 1),
+                (range.0.end() as u128 + 1, -1),
+            ]
+        })
+        .collect();
+    events.sort_unstable();
+
+    let mut busiest = IntervalSet::new();
+    let mut max_depth = 0;
+    let mut depth: i64 = 0;
+    for (i, (point, change)) in events.iter().enumerate() {
+        depth += change;
+        match events.get(i + 1) {
+            // Only look at the depth once every event at a point is applied.
+            Some((next, _)) if next == point => continue,
+            Some((next, _)) if depth > 0 => {
+                let section = Interval::new(*point as u64, (next - 1) as u64);
+                if depth as usize > max_depth {
+                    max_depth = depth as usize;
+                    busiest = IntervalSet::new();
+                }
+                if depth as usize == max_depth {
+                    busiest.insert(section);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (busiest, max_depth)
+}
+
+/// Find the elves outside of a smallest set whose ranges still cover every
+/// assigned section, choosing greedily the range reaching furthest.
+fn redundant_elves(ranges: &[&Range]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|i| (ranges[*i].0.start(), Reverse(ranges[*i].0.end())));
+
+    let mut needed = vec![false; ranges.len()];
+    let mut reach: Option<u64> = None;
+    let mut i = 0;
+    while i < order.len() {
+        let next_start = ranges[order[i]].0.start();
+        let uncovered = match reach {
+            Some(reach) if next_start <= reach.saturating_add(1) => reach.saturating_add(1),
+            _ => next_start,
+        };
+
+        let mut best = order[i];
+        while i < order.len() && ranges[order[i]].0.start() <= uncovered {
+            if ranges[order[i]].0.end() > ranges[best].0.end() {
+                best = order[i];
+            }
+            i += 1;
+        }
+        if reach.is_none_or(|reach| ranges[best].0.end() > reach) {
+            needed[best] = true;
+            reach = Some(ranges[best].0.end());
+        }
+    }
+
+    (0..ranges.len()).filter(|i| !needed[*i]).collect()
+}
+
+fn coverage(input: &[Assignment]) -> Coverage {
+    let ranges = elves(input);
+    let covered: IntervalSet = ranges.iter().map(|range| range.0).collect();
+    Coverage {
+        uncovered: covered.gaps(),
+        busiest: busiest_sections(&ranges),
+        distinct: covered.len(),
+        redundant: redundant_elves(&ranges),
+    }
+}
+
+fn print_coverage(input: &[Assignment]) {
+    let join = |set: &IntervalSet| {
+        set.intervals()
+            .iter()
+            .map(Interval::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let coverage = coverage(input);
+    println!("Distinct sections covered: {}", coverage.distinct);
+    println!("Uncovered sections: {}", join(&coverage.uncovered));
+    println!(
+        "Busiest sections ({} elves): {}",
+        coverage.busiest.1,
+        join(&coverage.busiest.0)
+    );
+    println!(
+        "Redundant elves: {}",
+        coverage
+            .redundant
+            .iter()
+            .map(|elf| elf_label(*elf))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Overlap {
+    first: usize,
+    second: usize,
+    sections: Interval,
+    /// Whether either elf's range fully contains the other's.
+    contained: bool,
+}
+
+/// Find every pair of elves anywhere in the input whose ranges overlap,
+/// sorted by elf numbers.
+///
+/// Sweeping through ranges by start, only ranges which haven't yet ended can
+/// overlap the next one, and each of those does.
+fn overlapping_pairs(input: &[Assignment]) -> Vec<Overlap> {
+    let ranges = elves(input);
+    let mut order: Vec<usize> = (0..ranges.len()).collect();
+    order.sort_by_key(|i| ranges[*i].0.start());
+
+    let mut overlaps = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    for elf in order {
+        let range = &ranges[elf].0;
+        active.retain(|other| ranges[*other].0.end() >= range.start());
+        for other in &active {
+            let other_range = &ranges[*other].0;
+            overlaps.push(Overlap {
+                first: elf.min(*other),
+                second: elf.max(*other),
+                sections: range.intersection(other_range).unwrap(),
+                contained: range.contains(other_range) || other_range.contains(range),
+            });
+        }
+        active.push(elf);
+    }
+
+    overlaps.sort_by_key(|overlap| (overlap.first, overlap.second));
+    overlaps
+}
+
+fn print_overlapping_pairs(input: &[Assignment]) {
+    for overlap in overlapping_pairs(input) {
+        println!(
+            "Elves {} and {} {} {}",
+            elf_label(overlap.first),
+            elf_label(overlap.second),
+            if overlap.contained {
+                "contain"
+            } else {
+                "overlap"
+            },
+            overlap.sections
+        );
+    }
 }
 
 fn parse_input(input_str: &str) -> Vec<Assignment> {
@@ -51,12 +276,25 @@ fn main() {
     println!("=========");
     println!("Part one: {}", print_elapsed_time(|| part_one(&input)));
     println!("Part two: {}", print_elapsed_time(|| part_two(&input)));
+
+    match env::args().nth(1).as_deref() {
+        Some("coverage") => print_coverage(&input),
+        Some("pairs") => print_overlapping_pairs(&input),
+        _ => {}
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "2-4,6-8
+2-3,4-5
+5-7,7-9
+2-8,3-7
+6-6,4-6
+2-6,4-8";
+
     #[test]
     fn test_given_example_04() {
         let input_str: String = String::from(
@@ -65,7 +303,7 @@ mod tests {
 5-7,7-9
 2-8,3-7
 6-6,4-6
-2-6,4-8"
+2-6,4-8",
         );
 
         let input = parse_input(&input_str);
@@ -73,4 +311,122 @@ mod tests {
         assert_eq!(part_one(&input), 2);
         assert_eq!(part_two(&input), 4);
     }
+
+    #[test]
+    fn test_huge_ranges_04() {
+        let input_str: String = String::from(
+            "1-4000000000,2-3999999999
+1-2000000000,2000000001-4000000000
+5-3000000000,2999999999-4000000000",
+        );
+
+        let input = parse_input(&input_str);
+
+        assert_eq!(part_one(&input), 1);
+        assert_eq!(part_two(&input), 2);
+    }
+
+    #[test]
+    fn test_parse_errors_04() {
+        assert_eq!(
+            "5-3,1-2".parse::<Assignment>().err(),
+            Some(ParseAssignmentError::Reversed { start: 5, end: 3 })
+        );
+        assert_eq!(
+            "1-2".parse::<Assignment>().err(),
+            Some(ParseAssignmentError::Malformed(String::from("1-2")))
+        );
+        assert!(matches!(
+            "1-x,2-3".parse::<Assignment>(),
+            Err(ParseAssignmentError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn test_coverage_04() {
+        let coverage = coverage(&parse_input(EXAMPLE));
+
+        assert_eq!(coverage.distinct, 8);
+        assert!(coverage.uncovered.is_empty());
+        assert_eq!(coverage.busiest.0.intervals(), &[Interval::new(6, 6)]);
+        assert_eq!(coverage.busiest.1, 8);
+        assert_eq!(coverage.redundant, vec![0, 1, 2, 3, 4, 7, 8, 9, 10, 11]);
+
+        let coverage = super::coverage(&parse_input("1-2,5-6\n5-8,20-30\n21-22,3-3"));
+
+        assert_eq!(coverage.distinct, 18);
+        assert_eq!(
+            coverage.uncovered.intervals(),
+            &[Interval::new(4, 4), Interval::new(9, 19)]
+        );
+        assert_eq!(
+            coverage.busiest.0.intervals(),
+            &[Interval::new(5, 6), Interval::new(21, 22)]
+        );
+        assert_eq!(coverage.redundant, vec![1, 4]);
+    }
+
+    #[test]
+    fn test_coverage_up_to_max_04() {
+        let max = u64::MAX;
+        let input = parse_input(&format!("1-{},{}-{}\n5-{},2-3", max, max - 1, max, max));
+
+        let coverage = coverage(&input);
+
+        assert_eq!(coverage.distinct, max as u128);
+        assert_eq!(
+            coverage.busiest.0.intervals(),
+            &[Interval::new(max - 1, max)]
+        );
+        assert_eq!(coverage.busiest.1, 3);
+        assert_eq!(coverage.redundant, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_overlapping_pairs_04() {
+        let input = parse_input(EXAMPLE);
+        let overlaps = overlapping_pairs(&input);
+
+        let ranges = elves(&input);
+        let mut expected = Vec::new();
+        for i in 0..ranges.len() {
+            for j in i + 1..ranges.len() {
+                if let Some(sections) = ranges[i].0.intersection(&ranges[j].0) {
+                    expected.push((i, j, sections));
+                }
+            }
+        }
+        assert_eq!(
+            overlaps
+                .iter()
+                .map(|o| (o.first, o.second, o.sections))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            overlaps[0],
+            Overlap {
+                first: 0,
+                second: 2,
+                sections: Interval::new(2, 3),
+                contained: true
+            }
+        );
+        assert_eq!(
+            overlaps[1],
+            Overlap {
+                first: 0,
+                second: 3,
+                sections: Interval::new(4, 4),
+                contained: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_elf_label_04() {
+        assert_eq!(elf_label(0), "1:first");
+        assert_eq!(elf_label(1), "1:second");
+        assert_eq!(elf_label(4), "3:first");
+    }
 }
@@ -1,5 +1,7 @@
-use aoc2022::{get_day_input, parse_input_lines, print_elapsed_time};
-use std::{char::ParseCharError, num::ParseIntError, str::FromStr};
+use aoc2022::rope::Rope;
+use aoc2022::{get_day_input, parse_input_lines, print_elapsed_time, Rng};
+use std::io::{self, BufRead};
+use std::{char::ParseCharError, env, fmt, num::ParseIntError, str::FromStr, thread, time};
 
 #[derive(Debug)]
 struct Instruction {
@@ -23,6 +25,12 @@ impl FromStr for Instruction {
     }
 }
 
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.num, self.from, self.to)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 struct Crate {
     letter: char,
@@ -37,7 +45,7 @@ impl FromStr for Crate {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct CrateSpecification {
     piles: Vec<Vec<Crate>>,
 }
@@ -73,69 +81,474 @@ impl FromStr for CrateSpecification {
     }
 }
 
+impl CrateSpecification {
+    /// Draw the piles as in the puzzle input, with the top `count` crates of
+    /// a pile optionally wrapped in the given escape codes.
+    fn draw(&self, highlight: Option<(usize, usize, &str, &str)>) -> String {
+        let height = self.piles.iter().map(Vec::len).max().unwrap_or(0);
+        let mut lines = Vec::new();
+        for level in (0..height).rev() {
+            let row: Vec<String> = self
+                .piles
+                .iter()
+                .enumerate()
+                .map(|(i, pile)| match (pile.get(level), highlight) {
+                    (Some(c), Some((index, count, start, end)))
+                        if i == index && level + count >= pile.len() =>
+                    {
+                        format!("{}[{}]{}", start, c.letter, end)
+                    }
+                    (Some(c), _) => format!("[{}]", c.letter),
+                    (None, _) => String::from("   "),
+                })
+                .collect();
+            lines.push(row.join(" "));
+        }
+        let footer: Vec<String> = (1..=self.piles.len())
+            .map(|i| format!("{:^3}", i))
+            .collect();
+        lines.push(footer.join(" "));
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for CrateSpecification {
+    /// Draw the piles as in the puzzle input, which parses back to the same
+    /// specification.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.draw(None))
+    }
+}
+
+/// A model of crane, deciding how crates end up ordered when moved.
+trait Crane {
+    /// Take `num` crates off the top of the pile, returning them in the order
+    /// they will be stacked onto the destination.
+    fn lift(&self, pile: &mut Vec<Crate>, num: usize) -> Vec<Crate>;
+
+    /// Whether the crane always moves crates from the top as one block, and
+    /// if so whether the block is reversed, so piles can be moved as ropes.
+    fn block_move(&self) -> Option<bool> {
+        None
+    }
+}
+
+/// Moves crates one at a time, reversing their order.
+struct CrateMover9000;
+
+/// Moves all the crates at once, keeping their order.
+struct CrateMover9001;
+
+/// Can lift at most `size` crates at once, which must be at least 1, moving
+/// each load in order.
+struct ChunkedCrane {
+    size: usize,
+}
+
+/// Slides crates out from the bottom of the pile rather than lifting from the
+/// top, keeping their order.
+struct BottomCrane;
+
+impl Crane for CrateMover9000 {
+    fn lift(&self, pile: &mut Vec<Crate>, num: usize) -> Vec<Crate> {
+        ChunkedCrane { size: 1 }.lift(pile, num)
+    }
+
+    fn block_move(&self) -> Option<bool> {
+        Some(true)
+    }
+}
+
+impl Crane for CrateMover9001 {
+    fn lift(&self, pile: &mut Vec<Crate>, num: usize) -> Vec<Crate> {
+        pile.split_off(pile.len() - num)
+    }
+
+    fn block_move(&self) -> Option<bool> {
+        Some(false)
+    }
+}
+
+impl Crane for ChunkedCrane {
+    fn lift(&self, pile: &mut Vec<Crate>, num: usize) -> Vec<Crate> {
+        assert!(self.size > 0, "Chunked crane can't lift 0 crates at a time");
+        let mut lifted = Vec::with_capacity(num);
+        let mut remaining = num;
+        while remaining > 0 {
+            let load = remaining.min(self.size);
+            lifted.extend(pile.drain(pile.len() - load..));
+            remaining -= load;
+        }
+        lifted
+    }
+}
+
+impl Crane for BottomCrane {
+    fn lift(&self, pile: &mut Vec<Crate>, num: usize) -> Vec<Crate> {
+        pile.drain(..num).collect()
+    }
+}
+
 #[derive(Debug)]
 struct Procedure {
     initial_state: CrateSpecification,
     instructions: Vec<Instruction>,
+    /// The line of the input the first instruction is on.
+    first_instruction_line: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum MoveErrorKind {
+    NoSuchPile { pile: usize, piles: usize },
+    NotEnoughCrates { pile: usize, size: usize },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct MoveError {
+    line: usize,
+    instruction: String,
+    kind: MoveErrorKind,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {} ({}): ", self.line, self.instruction)?;
+        match self.kind {
+            MoveErrorKind::NoSuchPile { pile, piles } => {
+                write!(f, "there is no pile {}, only piles 1 to {}", pile, piles)
+            }
+            MoveErrorKind::NotEnoughCrates { pile, size } => {
+                write!(f, "pile {} only has {} crates", pile, size)
+            }
+        }
+    }
 }
 
 impl Procedure {
-    fn implement_9000(&self) -> CrateSpecification {
+    fn run(&self, crane: &dyn Crane) -> CrateSpecification {
+        self.run_with(crane, |_, _, _| {})
+    }
+
+    fn run_with<F>(&self, crane: &dyn Crane, after_step: F) -> CrateSpecification
+    where
+        F: FnMut(usize, &Instruction, &CrateSpecification),
+    {
+        self.try_run_with(crane, after_step)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_run(&self, crane: &dyn Crane) -> Result<CrateSpecification, MoveError> {
+        self.try_run_with(crane, |_, _, _| {})
+    }
+
+    /// Run the procedure, calling `after_step` with each instruction's index,
+    /// the instruction and the resulting state, stopping at the first
+    /// instruction which can't be carried out.
+    fn try_run_with<F>(
+        &self,
+        crane: &dyn Crane,
+        mut after_step: F,
+    ) -> Result<CrateSpecification, MoveError>
+    where
+        F: FnMut(usize, &Instruction, &CrateSpecification),
+    {
         let mut state = self.initial_state.clone();
 
-        for instruction in &self.instructions {
-            let from_pile = &mut state.piles[instruction.from - 1];
-            let new_len = from_pile.len() - instruction.num;
-            let move_items: Vec<_> = from_pile.drain(new_len..).rev().collect();
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            self.move_crates(i, crane, &mut state, instruction.from, instruction.to)?;
+            after_step(i, instruction, &state);
+        }
+
+        Ok(state)
+    }
 
-            let to_pile = &mut state.piles[instruction.to - 1];
-            to_pile.extend(move_items);
+    /// Check that the `i`th instruction could move crates between the given
+    /// piles, out of `piles` whose sizes are given by index.
+    fn check_move(
+        &self,
+        i: usize,
+        from: usize,
+        to: usize,
+        piles: usize,
+        pile_size: impl Fn(usize) -> usize,
+    ) -> Result<(), MoveError> {
+        let instruction = &self.instructions[i];
+        let error = |kind| MoveError {
+            line: self.first_instruction_line + i,
+            instruction: instruction.to_string(),
+            kind,
+        };
+        for pile in [from, to] {
+            if pile == 0 || pile > piles {
+                return Err(error(MoveErrorKind::NoSuchPile { pile, piles }));
+            }
         }
+        let size = pile_size(from - 1);
+        if instruction.num > size {
+            return Err(error(MoveErrorKind::NotEnoughCrates { pile: from, size }));
+        }
+        Ok(())
+    }
+
+    /// Carry out the `i`th instruction on the state, but moving crates between
+    /// the given piles, checking that it can be done.
+    fn move_crates(
+        &self,
+        i: usize,
+        crane: &dyn Crane,
+        state: &mut CrateSpecification,
+        from: usize,
+        to: usize,
+    ) -> Result<(), MoveError> {
+        let piles = &state.piles;
+        self.check_move(i, from, to, piles.len(), |pile| piles[pile].len())?;
 
-        state
+        let move_items = crane.lift(&mut state.piles[from - 1], self.instructions[i].num);
+        state.piles[to - 1].extend(move_items);
+        Ok(())
     }
 
-    fn implement_9001(&self) -> CrateSpecification {
+    /// Treat `initial_state` as the state after the whole procedure, and undo
+    /// each instruction from the last to find the state before it.
+    ///
+    /// Only cranes moving crates as one block can be undone, by moving the
+    /// same block back with the same crane.
+    fn run_backwards(&self, crane: &dyn Crane) -> Result<CrateSpecification, MoveError> {
+        crane
+            .block_move()
+            .expect("Crane doesn't move crates as a block");
         let mut state = self.initial_state.clone();
 
-        for instruction in &self.instructions {
-            let from_pile = &mut state.piles[instruction.from - 1];
-            let new_len = from_pile.len() - instruction.num;
-            let move_items: Vec<_> = from_pile.drain(new_len..).collect();
-
-            let to_pile = &mut state.piles[instruction.to - 1];
-            to_pile.extend(move_items);
+        for (i, instruction) in self.instructions.iter().enumerate().rev() {
+            self.move_crates(i, crane, &mut state, instruction.to, instruction.from)?;
         }
 
-        state
+        Ok(state)
     }
 }
 
-fn part_one(input: &Procedure) -> String {
-    let new_state = input.implement_9000();
+impl Procedure {
+    /// Run the procedure with every pile held as a rope, so moving a block of
+    /// any size takes logarithmic time rather than copying every crate.
+    fn run_on_ropes(&self, crane: &dyn Crane) -> Result<CrateSpecification, MoveError> {
+        let reverse = crane
+            .block_move()
+            .expect("Crane doesn't move crates as a block");
+        let mut piles: Vec<Rope<Crate>> = self
+            .initial_state
+            .piles
+            .iter()
+            .map(|pile| pile.iter().cloned().collect())
+            .collect();
 
-    new_state
+        for (i, instruction) in self.instructions.iter().enumerate() {
+            self.check_move(i, instruction.from, instruction.to, piles.len(), |pile| {
+                piles[pile].len()
+            })?;
+            let from_pile = &mut piles[instruction.from - 1];
+            let mut move_items = from_pile.split_off(from_pile.len() - instruction.num);
+            if reverse {
+                move_items.reverse();
+            }
+            piles[instruction.to - 1].append(move_items);
+        }
+
+        Ok(CrateSpecification {
+            piles: piles.iter().map(Rope::to_vec).collect(),
+        })
+    }
+}
+
+fn top_crates(state: &CrateSpecification) -> String {
+    state
         .piles
         .iter()
-        .map(|v| v.last().unwrap().letter)
+        .map(|v| v.last().expect("Pile is empty").letter)
         .collect()
 }
 
-fn part_two(input: &Procedure) -> String {
-    let new_state = input.implement_9001();
-
-    new_state
+/// The top crate of each pile, showing `placeholder` for empty piles.
+fn top_crates_or(state: &CrateSpecification, placeholder: char) -> String {
+    state
         .piles
         .iter()
-        .map(|v| v.last().unwrap().letter)
+        .map(|v| v.last().map_or(placeholder, |c| c.letter))
         .collect()
 }
 
+fn part_one(input: &Procedure) -> String {
+    top_crates(&input.run(&CrateMover9000))
+}
+
+fn part_two(input: &Procedure) -> String {
+    top_crates(&input.run(&CrateMover9001))
+}
+
+/// Get a crane from its model name, where chunked cranes are followed by
+/// their size, returning the arguments left over.
+fn parse_crane<'a>(model: &str, args: &'a [String]) -> (Box<dyn Crane>, &'a [String]) {
+    match model {
+        "9000" => (Box::new(CrateMover9000), args),
+        "9001" => (Box::new(CrateMover9001), args),
+        "chunked" => {
+            let size = args.first().expect("Chunked crane needs a size");
+            match size.parse() {
+                Ok(0) | Err(_) => panic!("Invalid chunk size {}, expected at least 1", size),
+                Ok(size) => (Box::new(ChunkedCrane { size }), &args[1..]),
+            }
+        }
+        "bottom" => (Box::new(BottomCrane), args),
+        _ => panic!("Unknown crane model {}", model),
+    }
+}
+
+fn trace(input: &Procedure, crane: &dyn Crane) {
+    let result = input.try_run_with(crane, |i, instruction, state| {
+        println!(
+            "{}: {} => {}",
+            input.first_instruction_line + i,
+            instruction,
+            top_crates_or(state, '_')
+        );
+    });
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}
+
+fn render(input: &Procedure, crane: &dyn Crane) {
+    println!("{}\n", input.initial_state);
+    let result = input.try_run_with(crane, |i, instruction, state| {
+        println!(
+            "{}: {}\n{}\n",
+            input.first_instruction_line + i,
+            instruction,
+            state
+        );
+    });
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const HIGHLIGHT_START: &str = "\x1b[1;33m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+/// How `visualize` moves on from one frame to the next.
+enum Pace {
+    Delay(time::Duration),
+    /// Wait for enter to be pressed, or stop on `q`.
+    Step,
+}
+
+/// Replay the procedure in the terminal, highlighting the crates just moved.
+fn visualize(input: &Procedure, crane: &dyn Crane, pace: Pace) {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut stopped = false;
+
+    let mut show_frame = |heading: String, state: &CrateSpecification, highlight| {
+        if stopped {
+            return;
+        }
+        println!("{}{}\n\n{}", CLEAR_SCREEN, heading, state.draw(highlight));
+        match pace {
+            Pace::Delay(delay) => thread::sleep(delay),
+            Pace::Step => {
+                println!("\n[enter] to step, [q] to quit");
+                match lines.next() {
+                    Some(Ok(line)) if line.trim() != "q" => {}
+                    _ => stopped = true,
+                }
+            }
+        }
+    };
+
+    show_frame(String::from("Initial state"), &input.initial_state, None);
+    let result = input.try_run_with(crane, |i, instruction, state| {
+        show_frame(
+            format!("{}: {}", input.first_instruction_line + i, instruction),
+            state,
+            Some((
+                instruction.to - 1,
+                instruction.num,
+                HIGHLIGHT_START,
+                HIGHLIGHT_END,
+            )),
+        );
+    });
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}
+
+/// Generate a procedure with large piles where every instruction moves a
+/// random part of a pile, which is always possible. There must be at least
+/// two piles.
+fn generate_procedure(piles: usize, crates: usize, moves: usize, rng: &mut Rng) -> Procedure {
+    let initial_state = CrateSpecification {
+        piles: (0..piles)
+            .map(|_| {
+                (0..crates)
+                    .map(|_| Crate {
+                        letter: (b'A' + rng.below(26) as u8) as char,
+                    })
+                    .collect()
+            })
+            .collect(),
+    };
+
+    let mut sizes = vec![crates; piles];
+    let instructions = (0..moves)
+        .map(|_| {
+            let from = rng.below(piles as u64) as usize;
+            let to = (from + 1 + rng.below(piles as u64 - 1) as usize) % piles;
+            let num = rng.below(sizes[from] as u64 + 1) as usize;
+            sizes[from] -= num;
+            sizes[to] += num;
+            Instruction {
+                num,
+                from: from + 1,
+                to: to + 1,
+            }
+        })
+        .collect();
+
+    Procedure {
+        initial_state,
+        instructions,
+        first_instruction_line: crates + 3,
+    }
+}
+
+fn bench(piles: usize, crates: usize, moves: usize) {
+    assert!(
+        piles >= 2,
+        "Benchmarking needs at least 2 piles to move between"
+    );
+    let input = generate_procedure(piles, crates, moves, &mut Rng::new(5));
+    println!(
+        "Benchmarking {} moves between {} piles of {} crates:",
+        moves, piles, crates
+    );
+    for (name, crane) in [
+        ("9000", &CrateMover9000 as &dyn Crane),
+        ("9001", &CrateMover9001),
+    ] {
+        let vecs = print_elapsed_time(|| input.run(crane));
+        println!("{} (Vec): {}", name, top_crates_or(&vecs, '_'));
+        let ropes = print_elapsed_time(|| input.run_on_ropes(crane).unwrap());
+        println!("{} (Rope): {}", name, top_crates_or(&ropes, '_'));
+    }
+}
+
 fn parse_input(input_str: &str) -> Procedure {
     if let [first_part, second_part] = input_str.splitn(2, "\n\n").collect::<Vec<_>>()[..] {
         Procedure {
             initial_state: first_part.parse().expect("Malformed input"),
             instructions: parse_input_lines(second_part),
+            first_instruction_line: first_part.lines().count() + 2,
         }
     } else {
         panic!("Input is malformed")
@@ -147,6 +560,45 @@ fn main() {
     let input = parse_input(&input_str);
     println!("Day 05:");
     println!("=========");
+
+    // Modes run instead of the parts, as they may be checking an input the
+    // parts would panic on.
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("bench") {
+        let arg = |i: usize, default| {
+            args.get(i)
+                .map_or(default, |a: &String| a.parse().expect("Invalid argument"))
+        };
+        bench(arg(1, 9), arg(2, 100_000), arg(3, 10_000));
+        return;
+    }
+    if let [mode, model, rest @ ..] = &args[..] {
+        let (crane, rest) = parse_crane(model, rest);
+        match mode.as_str() {
+            "run" => match input.try_run(crane.as_ref()) {
+                Ok(state) => println!("Top crates: {}", top_crates_or(&state, '_')),
+                Err(e) => println!("{}", e),
+            },
+            "trace" => trace(&input, crane.as_ref()),
+            "render" => render(&input, crane.as_ref()),
+            "backwards" => match input.run_backwards(crane.as_ref()) {
+                Ok(state) => println!("Initial state:\n{}", state),
+                Err(e) => println!("{}", e),
+            },
+            "visualize" => {
+                let pace = match rest.first().map(String::as_str) {
+                    Some("step") => Pace::Step,
+                    delay => Pace::Delay(time::Duration::from_millis(
+                        delay.map_or(500, |d| d.parse().expect("Invalid delay")),
+                    )),
+                };
+                visualize(&input, crane.as_ref(), pace);
+            }
+            _ => panic!("Unknown mode {}", mode),
+        }
+        return;
+    }
+
     println!("Part one: {}", print_elapsed_time(|| part_one(&input)));
     println!("Part two: {}", print_elapsed_time(|| part_two(&input)));
 }
@@ -155,6 +607,17 @@ fn main() {
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "
+    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
     #[test]
     fn test_given_example_05() {
         let input_str: String = String::from(
@@ -176,4 +639,147 @@ move 1 from 1 to 2",
         assert_eq!(part_one(&input), "CMZ");
         assert_eq!(part_two(&input), "MCD");
     }
+
+    #[test]
+    fn test_crane_models_05() {
+        let input = parse_input(EXAMPLE);
+
+        assert_eq!(top_crates(&input.run(&ChunkedCrane { size: 1 })), "CMZ");
+        assert_eq!(top_crates(&input.run(&ChunkedCrane { size: 3 })), "MCD");
+        assert_eq!(top_crates(&input.run(&ChunkedCrane { size: 2 })), "MCZ");
+        assert_eq!(top_crates(&input.run(&BottomCrane)), "DCM");
+
+        let mut steps = Vec::new();
+        input.run_with(&CrateMover9001, |i, _, state| {
+            steps.push((i, state.piles.iter().map(Vec::len).collect::<Vec<_>>()))
+        });
+        assert_eq!(
+            steps,
+            vec![
+                (0, vec![3, 2, 1]),
+                (1, vec![0, 2, 4]),
+                (2, vec![2, 0, 4]),
+                (3, vec![1, 1, 4])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checked_run_05() {
+        let input = parse_input(&EXAMPLE.replace(
+            "move 2 from 2 to 1\nmove 1 from 1 to 2",
+            "move 3 from 2 to 1\nmove 1 from 0 to 2",
+        ));
+
+        assert_eq!(
+            input.try_run(&CrateMover9000).unwrap_err(),
+            MoveError {
+                line: 9,
+                instruction: String::from("move 3 from 2 to 1"),
+                kind: MoveErrorKind::NotEnoughCrates { pile: 2, size: 2 }
+            }
+        );
+
+        let input = Procedure {
+            instructions: input.instructions.into_iter().skip(3).collect(),
+            first_instruction_line: 10,
+            ..input
+        };
+        assert_eq!(
+            input.try_run(&CrateMover9000).unwrap_err().to_string(),
+            "Line 10 (move 1 from 0 to 2): there is no pile 0, only piles 1 to 3"
+        );
+    }
+
+    #[test]
+    fn test_top_crates_placeholder_05() {
+        let input = parse_input(" 1   2   3\n\nmove 0 from 1 to 2");
+        let state = input.try_run(&CrateMover9001).unwrap();
+
+        assert_eq!(top_crates_or(&state, '_'), "___");
+    }
+
+    #[test]
+    fn test_render_round_trip_05() {
+        let diagram = "    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 ";
+
+        let state: CrateSpecification = diagram.parse().unwrap();
+
+        assert_eq!(state.to_string(), diagram);
+        assert_eq!(state.to_string().parse::<CrateSpecification>(), Ok(state));
+
+        let empty = CrateSpecification {
+            piles: vec![Vec::new(); 10],
+        };
+        assert_eq!(empty.to_string().parse::<CrateSpecification>(), Ok(empty));
+    }
+
+    #[test]
+    fn test_draw_highlight_05() {
+        let state: CrateSpecification = "[A]    \n[B] [C]\n 1   2 ".parse().unwrap();
+
+        assert_eq!(
+            state.draw(Some((0, 1, "<", ">"))),
+            "<[A]>    \n[B] [C]\n 1   2 "
+        );
+        assert_eq!(
+            state.draw(Some((1, 1, "<", ">"))),
+            "[A]    \n[B] <[C]>\n 1   2 "
+        );
+    }
+
+    #[test]
+    fn test_run_on_ropes_05() {
+        let input = generate_procedure(4, 50, 200, &mut Rng::new(1));
+
+        assert_eq!(
+            input.run_on_ropes(&CrateMover9000),
+            input.try_run(&CrateMover9000)
+        );
+        assert_eq!(
+            input.run_on_ropes(&CrateMover9001),
+            input.try_run(&CrateMover9001)
+        );
+
+        for instructions in [
+            "move 1 from 1 to 2\nmove 3 from 2 to 1",
+            "move 1 from 3 to 1",
+        ] {
+            let input = parse_input(&format!("[A]    \n[B] [C]\n 1   2 \n\n{}", instructions));
+            assert!(input.try_run(&CrateMover9001).is_err());
+            assert_eq!(
+                input.run_on_ropes(&CrateMover9001),
+                input.try_run(&CrateMover9001)
+            );
+        }
+    }
+
+    #[test]
+    fn test_run_backwards_05() {
+        let input = parse_input(EXAMPLE);
+        for crane in [&CrateMover9000 as &dyn Crane, &CrateMover9001] {
+            let backwards = Procedure {
+                initial_state: input.run(crane),
+                instructions: parse_input_lines(EXAMPLE.split_once("\n\n").unwrap().1),
+                first_instruction_line: input.first_instruction_line,
+            };
+            assert_eq!(
+                backwards.run_backwards(crane),
+                Ok(input.initial_state.clone())
+            );
+        }
+
+        // The starting arrangement can't have had three crates moved onto the
+        // third pile.
+        assert_eq!(
+            input
+                .run_backwards(&CrateMover9001)
+                .unwrap_err()
+                .to_string(),
+            "Line 8 (move 3 from 1 to 3): pile 3 only has 1 crates"
+        );
+    }
 }
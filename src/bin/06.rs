@@ -1,24 +1,355 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, BufReader, Read, Write};
 
 use aoc2022::{get_day_input, print_elapsed_time};
 
-fn find_marker(input: &Input, window_size: usize) -> usize {
-    input
-        .windows(window_size)
-        .enumerate()
-        .filter(|(_, w)| HashSet::<char>::from_iter(w.iter().cloned()).len() == window_size)
-        .next()
-        .unwrap()
-        .0
-        + window_size
+/// Find how many symbols have been read when the last `window_size` were all
+/// different, if that ever happens.
+///
+/// Each symbol's last position is remembered, so on seeing a repeat the
+/// window can jump straight past the earlier copy.
+fn find_marker<T>(input: &[T], window_size: usize) -> Option<usize>
+where
+    T: Copy + Eq + Hash,
+{
+    let mut last_seen: HashMap<T, usize> = HashMap::new();
+    let mut start = 0;
+    for (i, symbol) in input.iter().enumerate() {
+        if let Some(last) = last_seen.insert(*symbol, i) {
+            start = start.max(last + 1);
+        }
+        if i + 1 - start == window_size {
+            return Some(i + 1);
+        }
+    }
+    None
 }
 
 fn part_one(input: &Input) -> usize {
-    find_marker(input, 4)
+    find_marker(input, PACKET_MARKER).expect("No start-of-packet marker")
 }
 
 fn part_two(input: &Input) -> usize {
-    find_marker(input, 14)
+    find_marker(input, MESSAGE_MARKER).expect("No start-of-message marker")
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Marker {
+    window_size: usize,
+    /// How many bytes had been read when the marker was found.
+    position: u64,
+}
+
+/// The longest run of different bytes ending at the latest one.
+struct DistinctRun {
+    last_seen: [Option<u64>; 256],
+    start: u64,
+}
+
+impl DistinctRun {
+    fn new() -> Self {
+        DistinctRun {
+            last_seen: [None; 256],
+            start: 0,
+        }
+    }
+
+    /// Take the byte at `position`, returning the length of the run before it
+    /// if the byte repeats one in that run, which cuts it short.
+    fn push(&mut self, position: u64, byte: u8) -> Option<u64> {
+        match self.last_seen[byte as usize].replace(position) {
+            Some(last) if last >= self.start => {
+                let broken = position - self.start;
+                self.start = last + 1;
+                Some(broken)
+            }
+            _ => None,
+        }
+    }
+
+    /// The length of the run ending at `position`.
+    fn len(&self, position: u64) -> u64 {
+        position + 1 - self.start
+    }
+}
+
+/// Follows a stream one byte at a time for a single window size, in the same
+/// way as `find_marker`.
+struct MarkerDetector {
+    window_size: usize,
+    run: DistinctRun,
+    distinct: bool,
+}
+
+impl MarkerDetector {
+    fn new(window_size: usize) -> Self {
+        MarkerDetector {
+            window_size,
+            run: DistinctRun::new(),
+            distinct: false,
+        }
+    }
+
+    /// Take the byte at `position`, returning whether it completes a marker:
+    /// the last `window_size` bytes are now all different, and weren't before.
+    fn push(&mut self, position: u64, byte: u8) -> bool {
+        self.run.push(position, byte);
+        let was_distinct = self.distinct;
+        self.distinct = self.run.len(position) >= self.window_size as u64;
+        self.distinct && !was_distinct
+    }
+}
+
+/// An iterator of every marker for several window sizes at once, read from
+/// any source in bounded memory and yielded as soon as each is found.
+struct MarkerStream<R: Read> {
+    bytes: io::Bytes<BufReader<R>>,
+    detectors: Vec<MarkerDetector>,
+    position: u64,
+    found: VecDeque<Marker>,
+}
+
+impl<R: Read> MarkerStream<R> {
+    fn new(source: R, window_sizes: &[usize]) -> Self {
+        MarkerStream {
+            bytes: BufReader::new(source).bytes(),
+            detectors: window_sizes
+                .iter()
+                .map(|w| MarkerDetector::new(*w))
+                .collect(),
+            position: 0,
+            found: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: Read> Iterator for MarkerStream<R> {
+    type Item = io::Result<Marker>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.found.is_empty() {
+            let byte = match self.bytes.next()? {
+                Ok(byte) => byte,
+                Err(e) => return Some(Err(e)),
+            };
+            for detector in &mut self.detectors {
+                if detector.push(self.position, byte) {
+                    self.found.push_back(Marker {
+                        window_size: detector.window_size,
+                        position: self.position + 1,
+                    });
+                }
+            }
+            self.position += 1;
+        }
+        self.found.pop_front().map(Ok)
+    }
+}
+
+/// Print every marker in standard input as it arrives.
+fn stream(window_sizes: &[usize]) {
+    for marker in MarkerStream::new(io::stdin().lock(), window_sizes) {
+        let marker = marker.expect("Failed to read input");
+        println!("Marker of {} at {}", marker.window_size, marker.position);
+    }
+}
+
+const PACKET_MARKER: usize = 4;
+const MESSAGE_MARKER: usize = 14;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum FrameKind {
+    Packet,
+    Message,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Frame {
+    kind: FrameKind,
+    payload: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum DecodeError {
+    /// A run of different bytes which is neither marker length.
+    BadMarker {
+        position: u64,
+        len: u64,
+    },
+    /// The bytes between markers weren't a validly encoded payload.
+    BadPayload {
+        position: u64,
+    },
+    Io(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::BadMarker { position, len } => write!(
+                f,
+                "Offset {}: run of {} different bytes is neither a packet nor a message marker",
+                position, len
+            ),
+            DecodeError::BadPayload { position } => {
+                write!(f, "Offset {}: payload isn't validly encoded", position)
+            }
+            DecodeError::Io(e) => write!(f, "Failed to read stream: {}", e),
+        }
+    }
+}
+
+/// Splits a datastream into frames, each a start-of-packet or
+/// start-of-message marker followed by its payload.
+///
+/// A marker is a run of exactly 4 or 14 different bytes, which starts with a
+/// repeat of the byte before it and is followed by a repeat of its last byte.
+/// The payload is the bytes after that repeat up to the next marker, with
+/// every byte doubled so a payload never contains a run of more than two
+/// different bytes. Anything before the first marker is ignored, even runs of
+/// different bytes of other lengths.
+struct Decoder<I: Iterator<Item = io::Result<u8>>> {
+    bytes: I,
+    run: DistinctRun,
+    position: u64,
+    /// The kind and start position of the frame being read, if a marker has
+    /// been seen yet.
+    current: Option<(FrameKind, u64)>,
+    /// Everything read since the end of the last marker.
+    pending: Vec<u8>,
+    /// The position of the first pending byte.
+    pending_start: u64,
+    decoded: VecDeque<Result<Frame, DecodeError>>,
+    done: bool,
+}
+
+impl<R: Read> Decoder<io::Bytes<BufReader<R>>> {
+    fn new(source: R) -> Self {
+        Decoder {
+            bytes: BufReader::new(source).bytes(),
+            run: DistinctRun::new(),
+            position: 0,
+            current: None,
+            pending: Vec::new(),
+            pending_start: 0,
+            decoded: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+/// Undo a payload's encoding: a repeat of the marker's last byte, then every
+/// byte of the payload twice.
+fn decode_payload(kind: FrameKind, start: u64, bytes: &[u8]) -> Result<Frame, DecodeError> {
+    let error = DecodeError::BadPayload { position: start };
+    let doubled = bytes.get(1..).ok_or(error.clone())?;
+    if doubled.len() % 2 != 0 || doubled.chunks(2).any(|pair| pair[0] != pair[1]) {
+        return Err(error);
+    }
+    Ok(Frame {
+        kind,
+        payload: doubled.iter().step_by(2).cloned().collect(),
+    })
+}
+
+impl<I: Iterator<Item = io::Result<u8>>> Decoder<I> {
+    /// Finish the frame being read, whose payload is everything pending but
+    /// the last `marker_len` bytes.
+    fn finish_frame(&mut self, marker_len: u64) {
+        let payload_len = self.pending.len() - marker_len as usize;
+        if let Some((kind, start)) = self.current.take() {
+            self.decoded
+                .push_back(decode_payload(kind, start, &self.pending[..payload_len]));
+        }
+        self.pending.clear();
+        self.pending_start = self.position;
+    }
+
+    /// Whether a run of different bytes of length `len` ending just before
+    /// the current position could be a marker. Runs reaching back into the
+    /// last marker or before are just part of the pending bytes.
+    fn is_marker(&self, len: u64) -> bool {
+        len >= PACKET_MARKER as u64 && self.position - len >= self.pending_start
+    }
+
+    /// Start a new frame after a marker of length `len` ending just before
+    /// the current position.
+    fn start_frame(&mut self, len: u64) {
+        let started = self.current.is_some();
+        self.finish_frame(len);
+        match len as usize {
+            PACKET_MARKER => self.current = Some((FrameKind::Packet, self.position)),
+            MESSAGE_MARKER => self.current = Some((FrameKind::Message, self.position)),
+            _ if !started => {}
+            _ => {
+                self.decoded.push_back(Err(DecodeError::BadMarker {
+                    position: self.position - len,
+                    len,
+                }));
+                self.done = true;
+            }
+        }
+    }
+}
+
+impl<I: Iterator<Item = io::Result<u8>>> Iterator for Decoder<I> {
+    type Item = Result<Frame, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.decoded.is_empty() && !self.done {
+            match self.bytes.next() {
+                Some(Ok(byte)) => {
+                    if let Some(len) = self.run.push(self.position, byte) {
+                        if self.is_marker(len) {
+                            self.start_frame(len);
+                        }
+                    }
+                    self.pending.push(byte);
+                    self.position += 1;
+                }
+                Some(Err(e)) => {
+                    self.decoded.push_back(Err(DecodeError::Io(e.to_string())));
+                    self.done = true;
+                }
+                None => {
+                    // The stream may end with a marker, which has no payload.
+                    let len = self.position.checked_sub(1).map_or(0, |p| self.run.len(p));
+                    if self.is_marker(len) {
+                        self.start_frame(len);
+                    }
+                    self.finish_frame(0);
+                    self.done = true;
+                }
+            }
+        }
+        self.decoded.pop_front()
+    }
+}
+
+/// Encode frames into a datastream which `Decoder` splits back into them,
+/// choosing marker bytes from the lowercase letters.
+fn encode(frames: &[Frame]) -> Vec<u8> {
+    let mut stream: Vec<u8> = Vec::new();
+    for frame in frames {
+        let len = match frame.kind {
+            FrameKind::Packet => PACKET_MARKER,
+            FrameKind::Message => MESSAGE_MARKER,
+        };
+        let mut marker: Vec<u8> = stream.last().cloned().into_iter().collect();
+        marker.extend(
+            (b'a'..=b'z')
+                .filter(|b| stream.last() != Some(b))
+                .take(len - marker.len()),
+        );
+        let last = *marker.last().unwrap();
+        stream.extend(marker);
+        stream.push(last);
+        stream.extend(frame.payload.iter().flat_map(|b| [*b, *b]));
+    }
+    stream
 }
 
 type Input = Vec<char>;
@@ -26,7 +357,58 @@ fn parse_input(input_str: &str) -> Input {
     input_str.chars().collect()
 }
 
+/// Get a frame given like `packet:payload` or `message:payload`.
+fn parse_frame(arg: &str) -> Frame {
+    let (kind, payload) = arg.split_once(':').expect("Malformed frame");
+    Frame {
+        kind: match kind {
+            "packet" => FrameKind::Packet,
+            "message" => FrameKind::Message,
+            _ => panic!("Unknown frame kind {}", kind),
+        },
+        payload: payload.as_bytes().to_vec(),
+    }
+}
+
+fn decode() {
+    for frame in Decoder::new(io::stdin().lock()) {
+        match frame {
+            Ok(frame) => println!(
+                "{:?}: {}",
+                frame.kind,
+                String::from_utf8_lossy(&frame.payload)
+            ),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
 fn main() {
+    // Modes work on standard input rather than the day's input, and may be
+    // writing a stream to standard output, so they run instead of the parts.
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("stream") => {
+            let window_sizes: Vec<usize> = if args.len() > 1 {
+                args[1..]
+                    .iter()
+                    .map(|w| w.parse().expect("Invalid window size"))
+                    .collect()
+            } else {
+                vec![PACKET_MARKER, MESSAGE_MARKER]
+            };
+            return stream(&window_sizes);
+        }
+        Some("decode") => return decode(),
+        Some("encode") => {
+            let frames: Vec<Frame> = args[1..].iter().map(|arg| parse_frame(arg)).collect();
+            return io::stdout()
+                .write_all(&encode(&frames))
+                .expect("Failed to write output");
+        }
+        _ => {}
+    }
+
     let input_str = get_day_input("06");
     let input = parse_input(&input_str);
     println!("Day 06:");
@@ -72,4 +454,119 @@ mod tests {
         assert_eq!(part_one(&input), 11);
         assert_eq!(part_two(&input), 26);
     }
+
+    #[test]
+    fn test_find_marker_06() {
+        let signal = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+
+        assert_eq!(find_marker(signal.as_bytes(), 4), Some(7));
+        assert_eq!(find_marker(signal.as_bytes(), 14), Some(19));
+        assert_eq!(find_marker(&parse_input(signal), 1), Some(1));
+        assert_eq!(find_marker(&parse_input("abcabc"), 4), None);
+        assert_eq!(find_marker(b"abc", 4), None);
+    }
+
+    #[test]
+    fn test_marker_stream_06() {
+        let signal = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+
+        let markers: Vec<Marker> = MarkerStream::new(signal.as_bytes(), &[4, 14])
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert_eq!(
+            markers.first(),
+            Some(&Marker {
+                window_size: 4,
+                position: 7
+            })
+        );
+        assert_eq!(
+            markers.iter().find(|m| m.window_size == 14),
+            Some(&Marker {
+                window_size: 14,
+                position: 19
+            })
+        );
+
+        let markers: Vec<u64> = MarkerStream::new("abcabcaabcd".as_bytes(), &[3])
+            .map(|m| m.unwrap().position)
+            .collect();
+        assert_eq!(markers, vec![3, 10]);
+    }
+
+    #[test]
+    fn test_encode_decode_06() {
+        let frames = vec![
+            Frame {
+                kind: FrameKind::Packet,
+                payload: b"hello".to_vec(),
+            },
+            Frame {
+                kind: FrameKind::Message,
+                payload: b"abcdefghijklmnopqrstuvwxyz".to_vec(),
+            },
+            Frame {
+                kind: FrameKind::Packet,
+                payload: Vec::new(),
+            },
+            Frame {
+                kind: FrameKind::Packet,
+                payload: b"dd".to_vec(),
+            },
+        ];
+
+        let stream = encode(&frames);
+        assert_eq!(&stream[..15], b"abcddhheelllloo");
+        let decoded: Vec<Frame> = Decoder::new(stream.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(decoded, frames);
+    }
+
+    #[test]
+    fn test_decode_errors_06() {
+        let decoded: Vec<_> = Decoder::new("xxabcdeeebcddhhhxyzwvv".as_bytes()).collect();
+        assert_eq!(
+            decoded,
+            vec![
+                Ok(Frame {
+                    kind: FrameKind::Packet,
+                    payload: b"h".to_vec()
+                }),
+                Err(DecodeError::BadMarker {
+                    position: 15,
+                    len: 6
+                })
+            ]
+        );
+
+        let decoded: Vec<_> = Decoder::new("abcddxyy".as_bytes()).collect();
+        assert_eq!(decoded, vec![Err(DecodeError::BadPayload { position: 4 })]);
+    }
+
+    #[test]
+    fn test_decode_any_stream_06() {
+        for signal in [
+            "mjqjpqmgbljsphdztnvjfqwrcgsmlb",
+            "bvwbjplbgvbhsrlpgdmjqwftvncz",
+            "nppdvjthqldpwncqszvftbrmjlhg",
+            "nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg",
+            "zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw",
+            "abcdbefgg",
+        ] {
+            Decoder::new(signal.as_bytes()).for_each(drop);
+        }
+
+        let mut rng = aoc2022::Rng::new(6);
+        for _ in 0..500 {
+            let len = rng.below(60) as usize;
+            let alphabet = 2 + rng.below(20);
+            let stream: Vec<u8> = (0..len).map(|_| b'a' + rng.below(alphabet) as u8).collect();
+            Decoder::new(stream.as_slice()).for_each(drop);
+        }
+
+        let decoded: Vec<_> = Decoder::new("abcdbefgg".as_bytes()).collect();
+        assert_eq!(decoded, vec![Err(DecodeError::BadPayload { position: 4 })]);
+    }
 }
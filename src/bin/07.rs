@@ -1,4 +1,8 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::{env, fmt};
 
 use aoc2022::{get_day_input, print_elapsed_time};
 
@@ -8,15 +12,163 @@ struct File {
     size: usize,
 }
 
-type Path = Vec<String>;
+/// An index of a directory in a `FileSystem`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+struct NodeId(usize);
+
+/// A directory, or one of the files in one.
+#[derive(Debug, PartialEq, Clone)]
+enum Entry {
+    Directory(NodeId),
+    /// A directory and the index of one of its files.
+    File(NodeId, usize),
+}
 
 #[derive(Debug, PartialEq, Clone)]
 struct Directory {
     name: String,
-    dirs: Vec<Path>,
+    parent: Option<NodeId>,
+    dirs: Vec<NodeId>,
     files: Vec<File>,
+    /// Where to find each subdirectory and file by name.
+    dir_index: HashMap<String, NodeId>,
+    file_index: HashMap<String, usize>,
+    /// The total size of everything within, once computed.
+    size: usize,
 }
 
+/// A tree of directories stored in an arena, where a directory is always
+/// added after its parent.
+#[derive(Debug, PartialEq, Clone)]
+struct FileSystem {
+    dirs: Vec<Directory>,
+}
+
+impl FileSystem {
+    const ROOT: NodeId = NodeId(0);
+
+    fn new() -> Self {
+        FileSystem {
+            dirs: vec![Directory {
+                name: String::from("/"),
+                parent: None,
+                dirs: Vec::new(),
+                files: Vec::new(),
+                dir_index: HashMap::new(),
+                file_index: HashMap::new(),
+                size: 0,
+            }],
+        }
+    }
+
+    fn dir(&self, id: NodeId) -> &Directory {
+        &self.dirs[id.0]
+    }
+
+    fn child(&self, id: NodeId, name: &str) -> Option<NodeId> {
+        self.dir(id).dir_index.get(name).cloned()
+    }
+
+    fn file(&self, id: NodeId, name: &str) -> Option<&File> {
+        let dir = self.dir(id);
+        dir.file_index.get(name).map(|i| &dir.files[*i])
+    }
+
+    /// Get the named subdirectory, adding it if it doesn't exist yet.
+    fn add_dir(&mut self, parent: NodeId, name: &str) -> NodeId {
+        if let Some(id) = self.child(parent, name) {
+            return id;
+        }
+        let id = NodeId(self.dirs.len());
+        self.dirs.push(Directory {
+            name: String::from(name),
+            parent: Some(parent),
+            dirs: Vec::new(),
+            files: Vec::new(),
+            dir_index: HashMap::new(),
+            file_index: HashMap::new(),
+            size: 0,
+        });
+        self.dirs[parent.0].dirs.push(id);
+        self.dirs[parent.0].dir_index.insert(String::from(name), id);
+        id
+    }
+
+    /// Add a file, unless there's already one with the same name.
+    fn add_file(&mut self, dir: NodeId, file: File) {
+        let dir = &mut self.dirs[dir.0];
+        if !dir.file_index.contains_key(&file.name) {
+            dir.file_index.insert(file.name.clone(), dir.files.len());
+            dir.files.push(file);
+        }
+    }
+
+    /// Find the directory at a path of names from the root.
+    fn lookup(&self, path: &[&str]) -> Option<NodeId> {
+        path.iter()
+            .try_fold(Self::ROOT, |id, name| self.child(id, name))
+    }
+
+    /// The full path of something named within a directory.
+    fn child_path(&self, id: NodeId, name: &str) -> String {
+        let path = self.path(id);
+        format!("{}/{}", path.trim_end_matches('/'), name)
+    }
+
+    /// The full path of a directory, like `/a/e`.
+    fn path(&self, id: NodeId) -> String {
+        let mut names = Vec::new();
+        let mut current = id;
+        while let Some(parent) = self.dir(current).parent {
+            names.push(self.dir(current).name.as_str());
+            current = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    /// How many directories down from the root a directory is.
+    fn depth(&self, id: NodeId) -> usize {
+        std::iter::successors(self.dir(id).parent, |parent| self.dir(*parent).parent).count()
+    }
+
+    fn entry_path(&self, entry: &Entry) -> String {
+        match entry {
+            Entry::Directory(id) => self.path(*id),
+            Entry::File(id, i) => self.child_path(*id, &self.dir(*id).files[*i].name),
+        }
+    }
+
+    fn entry_size(&self, entry: &Entry) -> usize {
+        match entry {
+            Entry::Directory(id) => self.size(*id),
+            Entry::File(id, i) => self.dir(*id).files[*i].size,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (NodeId, &Directory)> {
+        self.dirs
+            .iter()
+            .enumerate()
+            .map(|(i, dir)| (NodeId(i), dir))
+    }
+
+    /// Work out every directory's size, from the most recently added up, so
+    /// each subdirectory is done before its parent.
+    fn compute_sizes(&mut self) {
+        for i in (0..self.dirs.len()).rev() {
+            let files: usize = self.dirs[i].files.iter().map(|f| f.size).sum();
+            let dirs: usize = self.dirs[i].dirs.iter().map(|d| self.dirs[d.0].size).sum();
+            self.dirs[i].size = files + dirs;
+        }
+    }
+
+    fn size(&self, id: NodeId) -> usize {
+        self.dir(id).size
+    }
+}
+
+/// One step along a path given to `cd` or `ls`.
 #[derive(Debug, PartialEq, Clone)]
 enum CdArg {
     Root,
@@ -32,78 +184,128 @@ enum FileInfo {
 
 #[derive(Debug, PartialEq, Clone)]
 enum Command {
-    Cd(CdArg),
-    Ls(Vec<FileInfo>),
+    Cd(Vec<CdArg>),
+    /// A listing of the directory at a path, which is empty for the current
+    /// directory, with the line each entry is on.
+    Ls(Vec<CdArg>, Vec<(usize, FileInfo)>),
+    Pwd,
+    Unknown(String),
 }
 
-fn dir_size(dir: &Directory, dir_set: &HashMap<Path, Directory>) -> usize {
-    let mut size: usize = 0;
-    for dir_path in &dir.dirs {
-        size += dir_size(dir_set.get(dir_path).unwrap(), dir_set);
-    }
-    for file in &dir.files {
-        size += file.size;
+#[derive(Debug, PartialEq, Clone)]
+enum IssueKind {
+    DuplicateListing { dir: String, first_line: usize },
+    ConflictingSize { file: String, sizes: (usize, usize) },
+    UnlistedDirectory { dir: String },
+    UpFromRoot,
+    UnknownCommand(String),
+}
+
+/// Something in a transcript which doesn't add up, and the line it's on.
+#[derive(Debug, PartialEq, Clone)]
+struct Issue {
+    line: usize,
+    kind: IssueKind,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Line {}: ", self.line)?;
+        match &self.kind {
+            IssueKind::DuplicateListing { dir, first_line } => {
+                write!(f, "{} was already listed on line {}", dir, first_line)
+            }
+            IssueKind::ConflictingSize { file, sizes } => {
+                write!(
+                    f,
+                    "{} was listed with size {}, now {}",
+                    file, sizes.0, sizes.1
+                )
+            }
+            IssueKind::UnlistedDirectory { dir } => {
+                write!(f, "moved into {}, which was never listed", dir)
+            }
+            IssueKind::UpFromRoot => write!(f, "moved up from the root"),
+            IssueKind::UnknownCommand(command) => write!(f, "unknown command {:?}", command),
+        }
     }
-    size
 }
 
-fn construct_fs(input: &Input) -> HashMap<Path, Directory> {
-    let mut dirs: HashMap<Path, Directory> = HashMap::new();
-    let mut curr_dir: Vec<String> = Vec::new();
+/// Follow a path from a directory, noting any steps which don't work.
+fn walk(
+    fs: &mut FileSystem,
+    from: NodeId,
+    path: &[CdArg],
+    mut report: impl FnMut(IssueKind),
+) -> NodeId {
+    let mut dir = from;
+    for step in path {
+        match step {
+            CdArg::Root => dir = FileSystem::ROOT,
+            CdArg::Up => match fs.dir(dir).parent {
+                Some(parent) => dir = parent,
+                None => report(IssueKind::UpFromRoot),
+            },
+            CdArg::Into(dirname) => {
+                if fs.child(dir, dirname).is_none() {
+                    report(IssueKind::UnlistedDirectory {
+                        dir: fs.child_path(dir, dirname),
+                    });
+                }
+                dir = fs.add_dir(dir, dirname);
+            }
+        }
+    }
+    dir
+}
 
-    // Insert the root node
-    dirs.insert(
-        curr_dir.clone(),
-        Directory {
-            name: String::from("/"),
-            dirs: Vec::new(),
-            files: Vec::new(),
-        },
-    );
+/// Rebuild the filesystem from a transcript, noting every issue found on
+/// the way.
+///
+/// Files listed more than once are only counted the first time, and moving
+/// up from the root stays there.
+fn replay(input: &Input) -> (FileSystem, Vec<Issue>) {
+    let mut fs = FileSystem::new();
+    let mut issues = Vec::new();
+    let mut listed: HashMap<NodeId, usize> = HashMap::new();
+    let mut curr_dir = FileSystem::ROOT;
 
-    for command in input {
+    for (line, command) in input {
+        let line = *line;
+        let mut report = |kind| issues.push(Issue { line, kind });
         match command {
-            Command::Cd(CdArg::Root) => curr_dir = Vec::new(),
-            Command::Cd(CdArg::Up) => {
-                curr_dir.pop().expect("Went up from root");
-            }
-            Command::Cd(CdArg::Into(dirname)) => {
-                curr_dir.push(String::from(dirname));
-                if !dirs.contains_key(&curr_dir) {
-                    dirs.insert(
-                        curr_dir.clone(),
-                        Directory {
-                            name: String::from(dirname),
-                            dirs: Vec::new(),
-                            files: Vec::new(),
-                        },
-                    );
+            Command::Cd(path) => curr_dir = walk(&mut fs, curr_dir, path, report),
+            Command::Pwd => {}
+            Command::Unknown(command) => report(IssueKind::UnknownCommand(command.clone())),
+            Command::Ls(path, file_infos) => {
+                let dir = walk(&mut fs, curr_dir, path, &mut report);
+                if let Some(first_line) = listed.insert(dir, line) {
+                    report(IssueKind::DuplicateListing {
+                        dir: fs.path(dir),
+                        first_line,
+                    });
                 }
-            }
-            Command::Ls(file_infos) => {
-                for file_info in file_infos {
+                for (file_line, file_info) in file_infos {
                     match file_info {
                         FileInfo::Directory(dirname) => {
-                            let mut new_dir = curr_dir.clone();
-                            new_dir.push(String::from(dirname));
-                            if !dirs.contains_key(&new_dir) {
-                                dirs.insert(
-                                    new_dir.clone(),
-                                    Directory {
-                                        name: String::from(dirname),
-                                        dirs: Vec::new(),
-                                        files: Vec::new(),
-                                    },
-                                );
-                            }
-                            dirs.entry(curr_dir.clone()).and_modify(|dir| {
-                                dir.dirs.push(new_dir.clone());
-                            });
+                            fs.add_dir(dir, dirname);
                         }
                         FileInfo::File(file) => {
-                            dirs.entry(curr_dir.clone()).and_modify(|dir| {
-                                dir.files.push(file.clone());
-                            });
+                            let existing = fs.file(dir, &file.name);
+                            match existing {
+                                None => fs.add_file(dir, file.clone()),
+                                Some(existing) if existing.size != file.size => {
+                                    let kind = IssueKind::ConflictingSize {
+                                        file: fs.child_path(dir, &file.name),
+                                        sizes: (existing.size, file.size),
+                                    };
+                                    issues.push(Issue {
+                                        line: *file_line,
+                                        kind,
+                                    });
+                                }
+                                Some(_) => {}
+                            }
                         }
                     }
                 }
@@ -111,84 +313,918 @@ fn construct_fs(input: &Input) -> HashMap<Path, Directory> {
         }
     }
 
-    dirs
+    fs.compute_sizes();
+    (fs, issues)
+}
+
+fn construct_fs(input: &Input) -> FileSystem {
+    replay(input).0
+}
+
+fn print_issues(input: &Input) {
+    let (_, issues) = replay(input);
+    if issues.is_empty() {
+        println!("Transcript is consistent");
+    }
+    for issue in issues {
+        println!("{}", issue);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Disk {
+    size: usize,
+    /// The free space needed.
+    needed: usize,
+}
+
+impl Disk {
+    const PUZZLE: Disk = Disk {
+        size: 70_000_000,
+        needed: 30_000_000,
+    };
+
+    fn to_free(&self, fs: &FileSystem) -> usize {
+        let free = self.size.saturating_sub(fs.size(FileSystem::ROOT));
+        self.needed.saturating_sub(free)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum DeletionStrategy {
+    /// Delete the one smallest directory that frees enough.
+    SmallestDirectory,
+    /// Delete the directories adding up to the least that frees enough.
+    Directories,
+    /// Delete the directories and files adding up to the least that frees
+    /// enough.
+    Entries,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Plan {
+    deletions: Vec<Entry>,
+    freed: usize,
+    to_free: usize,
+    /// The strategy followed, which is the smallest directory when there were
+    /// too many ways of combining candidates to search them all.
+    strategy: DeletionStrategy,
+}
+
+/// Something which could be deleted, where deleting it also deletes the
+/// entries up to `end` in a pre-order list of them.
+struct Candidate {
+    entry: Entry,
+    size: usize,
+    end: usize,
+}
+
+fn candidates(fs: &FileSystem, with_files: bool) -> Vec<Candidate> {
+    fn visit(fs: &FileSystem, id: NodeId, with_files: bool, out: &mut Vec<Candidate>) {
+        let index = out.len();
+        out.push(Candidate {
+            entry: Entry::Directory(id),
+            size: fs.size(id),
+            end: 0,
+        });
+        if with_files {
+            for (i, file) in fs.dir(id).files.iter().enumerate() {
+                out.push(Candidate {
+                    entry: Entry::File(id, i),
+                    size: file.size,
+                    end: out.len() + 1,
+                });
+            }
+        }
+        for child in &fs.dir(id).dirs {
+            visit(fs, *child, with_files, out);
+        }
+        out[index].end = out.len();
+    }
+
+    let mut out = Vec::new();
+    visit(fs, FileSystem::ROOT, with_files, &mut out);
+    out
+}
+
+/// A set of totals below some width.
+trait Totals: Sized {
+    fn zero(width: usize) -> Self;
+
+    /// A copy with `shift` added to each total, dropping any reaching `width`.
+    fn shifted(&self, shift: usize, width: usize) -> Self;
+
+    /// Add every total from `other`, calling `new` with each not already here.
+    fn merge(&mut self, other: Self, new: impl FnMut(usize));
+
+    fn least_from(&self, from: usize, width: usize) -> Option<usize>;
+
+    /// How many words or totals are stored, for bounding a search.
+    fn cost(&self) -> usize;
+}
+
+/// Totals as a bitset, for when the width is small enough.
+struct Bitset(Vec<u64>);
+
+impl Totals for Bitset {
+    fn zero(width: usize) -> Self {
+        let mut bits = vec![0; width.div_ceil(64)];
+        bits[0] = 1;
+        Bitset(bits)
+    }
+
+    fn shifted(&self, shift: usize, width: usize) -> Self {
+        let bits = &self.0;
+        let (words, offset) = (shift / 64, shift % 64);
+        let mut out = vec![0; bits.len()];
+        for i in words..bits.len() {
+            out[i] = bits[i - words] << offset;
+            if offset > 0 && i > words {
+                out[i] |= bits[i - words - 1] >> (64 - offset);
+            }
+        }
+        if !width.is_multiple_of(64) {
+            *out.last_mut().unwrap() &= (1 << (width % 64)) - 1;
+        }
+        Bitset(out)
+    }
+
+    fn merge(&mut self, other: Self, mut new: impl FnMut(usize)) {
+        for (word, (bits, other)) in self.0.iter_mut().zip(other.0).enumerate() {
+            let mut added = other & !*bits;
+            while added != 0 {
+                new(word * 64 + added.trailing_zeros() as usize);
+                added &= added - 1;
+            }
+            *bits |= other;
+        }
+    }
+
+    fn least_from(&self, from: usize, width: usize) -> Option<usize> {
+        (from..width).find(|total| self.0[total / 64] & (1 << (total % 64)) != 0)
+    }
+
+    fn cost(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// Totals as a sorted list, for when they're spread too thinly for a bitset.
+struct SortedTotals(Vec<usize>);
+
+impl Totals for SortedTotals {
+    fn zero(_: usize) -> Self {
+        SortedTotals(vec![0])
+    }
+
+    fn shifted(&self, shift: usize, width: usize) -> Self {
+        SortedTotals(
+            self.0
+                .iter()
+                .map(|total| total + shift)
+                .take_while(|total| *total < width)
+                .collect(),
+        )
+    }
+
+    fn merge(&mut self, other: Self, mut new: impl FnMut(usize)) {
+        let mut merged = Vec::with_capacity(self.0.len() + other.0.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.0.len() || j < other.0.len() {
+            match (self.0.get(i), other.0.get(j)) {
+                (Some(a), Some(b)) if a == b => {
+                    merged.push(*a);
+                    i += 1;
+                    j += 1;
+                }
+                (Some(a), Some(b)) if a < b => {
+                    merged.push(*a);
+                    i += 1;
+                }
+                (Some(a), None) => {
+                    merged.push(*a);
+                    i += 1;
+                }
+                (_, Some(b)) => {
+                    new(*b);
+                    merged.push(*b);
+                    j += 1;
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+        self.0 = merged;
+    }
+
+    fn least_from(&self, from: usize, _: usize) -> Option<usize> {
+        let i = self.0.partition_point(|total| *total < from);
+        self.0.get(i).cloned()
+    }
+
+    fn cost(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// The widest range of totals kept as a bitset, beyond which they're listed.
+/// Noting which candidate reached each total takes two bytes per total.
+const BITSET_WIDTH: usize = 1 << 26;
+
+/// The most words or totals a search may go through, and may hold at once.
+const SEARCH_WORK: usize = 1 << 27;
+const SEARCH_MEMORY: usize = 1 << 22;
+
+/// A search which would have gone past `SEARCH_WORK` or `SEARCH_MEMORY`.
+#[derive(Debug, PartialEq, Clone)]
+struct SearchTooLarge;
+
+/// Go through candidates in pre-order, where either a candidate is deleted
+/// and everything inside it skipped, or the next one is considered, finding
+/// the least total below `width` that's at least `to_free`.
+///
+/// Each reachable total is passed to `note` with the candidate which first
+/// reached it. As totals only become reachable once everything inside the
+/// candidate which reached them has been skipped, following those notes back
+/// never finds one candidate inside another.
+///
+/// Every candidate still open holds its own copy of the totals, and spread
+/// out totals can double with each candidate, so the search gives up rather
+/// than going past its limits.
+fn search<T: Totals>(
+    candidates: &[Candidate],
+    to_free: usize,
+    width: usize,
+    mut note: impl FnMut(usize, usize),
+) -> Result<Option<usize>, SearchTooLarge> {
+    let mut reached = T::zero(width);
+    let mut pending: Vec<Vec<(usize, T)>> = (0..=candidates.len()).map(|_| Vec::new()).collect();
+    let (mut work, mut held) = (0, 0);
+    for position in 0..=candidates.len() {
+        for (index, totals) in std::mem::take(&mut pending[position]) {
+            work += reached.cost() + totals.cost();
+            held -= totals.cost();
+            reached.merge(totals, |total| note(total, index));
+        }
+        if let Some(candidate) = candidates.get(position) {
+            if candidate.size < to_free {
+                let totals = reached.shifted(candidate.size, width);
+                work += totals.cost();
+                held += totals.cost();
+                pending[candidate.end].push((position, totals));
+            }
+        }
+        if work > SEARCH_WORK || held + reached.cost() > SEARCH_MEMORY {
+            return Err(SearchTooLarge);
+        }
+    }
+    Ok(reached.least_from(to_free, width))
+}
+
+/// Follow the notes of which candidate first reached each total back from
+/// `total` to nothing.
+fn backtrack(
+    candidates: &[Candidate],
+    mut total: usize,
+    first: impl Fn(usize) -> usize,
+) -> Vec<usize> {
+    let mut chosen = Vec::new();
+    while total > 0 {
+        let index = first(total);
+        chosen.push(index);
+        total -= candidates[index].size;
+    }
+    chosen.reverse();
+    chosen
+}
+
+/// Find the candidates adding up to the least that's at least `to_free`,
+/// where none is inside another.
+fn least_covering(
+    candidates: &[Candidate],
+    to_free: usize,
+) -> Result<Option<Vec<usize>>, SearchTooLarge> {
+    // The smallest single candidate which is enough by itself; any set
+    // needing more than one only uses smaller ones, and never goes over by as
+    // much as the largest of those.
+    let single = (0..candidates.len())
+        .filter(|i| candidates[*i].size >= to_free)
+        .min_by_key(|i| candidates[*i].size);
+    let largest = candidates
+        .iter()
+        .map(|c| c.size)
+        .filter(|size| *size < to_free)
+        .max()
+        .unwrap_or(0);
+    let width = to_free.saturating_add(largest);
+
+    let several = if width <= BITSET_WIDTH && candidates.len() <= u16::MAX as usize {
+        let mut first: Vec<u16> = vec![0; width];
+        search::<Bitset>(candidates, to_free, width, |total, index| {
+            first[total] = index as u16
+        })?
+        .map(|best| backtrack(candidates, best, |total| first[total] as usize))
+    } else {
+        // Each total is only noted once, so the notes can be sorted and
+        // searched afterwards, which is quicker than hashing them.
+        let mut first: Vec<(usize, usize)> = Vec::new();
+        search::<SortedTotals>(candidates, to_free, width, |total, index| {
+            first.push((total, index))
+        })?
+        .map(|best| {
+            first.sort_unstable();
+            backtrack(candidates, best, |total| {
+                first[first.partition_point(|(noted, _)| *noted < total)].1
+            })
+        })
+    };
+
+    let total = |chosen: &[usize]| chosen.iter().map(|i| candidates[*i].size).sum::<usize>();
+    Ok(match (single, several) {
+        (Some(single), Some(several)) if total(&several) < candidates[single].size => Some(several),
+        (Some(single), _) => Some(vec![single]),
+        (None, several) => several,
+    })
+}
+
+/// Work out what to delete to leave enough free space on a disk, or `None`
+/// if deleting everything isn't enough. If there are too many ways of
+/// combining directories or entries, this deletes the smallest directory.
+fn plan_deletion(fs: &FileSystem, disk: &Disk, strategy: DeletionStrategy) -> Option<Plan> {
+    let to_free = disk.to_free(fs);
+    if to_free > fs.size(FileSystem::ROOT) {
+        return None;
+    }
+    if to_free == 0 {
+        return Some(Plan {
+            deletions: Vec::new(),
+            freed: 0,
+            to_free,
+            strategy,
+        });
+    }
+
+    let deletions = match strategy {
+        DeletionStrategy::SmallestDirectory => {
+            let (id, _) = fs
+                .iter()
+                .filter(|(id, _)| fs.size(*id) >= to_free)
+                .min_by_key(|(id, _)| fs.size(*id))?;
+            vec![Entry::Directory(id)]
+        }
+        DeletionStrategy::Directories | DeletionStrategy::Entries => {
+            let candidates = candidates(fs, strategy == DeletionStrategy::Entries);
+            match least_covering(&candidates, to_free) {
+                Ok(chosen) => chosen?
+                    .into_iter()
+                    .map(|i| candidates[i].entry.clone())
+                    .collect(),
+                Err(SearchTooLarge) => {
+                    return plan_deletion(fs, disk, DeletionStrategy::SmallestDirectory)
+                }
+            }
+        }
+    };
+    let freed = deletions.iter().map(|entry| fs.entry_size(entry)).sum();
+    Some(Plan {
+        deletions,
+        freed,
+        to_free,
+        strategy,
+    })
+}
+
+fn print_plan(input: &Input, strategy: &str, disk: &Disk) {
+    let strategy = match strategy {
+        "smallest" => DeletionStrategy::SmallestDirectory,
+        "dirs" => DeletionStrategy::Directories,
+        "entries" => DeletionStrategy::Entries,
+        _ => panic!(
+            "Unknown strategy {}, expected smallest, dirs or entries",
+            strategy
+        ),
+    };
+    let fs = construct_fs(input);
+    let Some(plan) = plan_deletion(&fs, disk, strategy) else {
+        println!("Deleting everything doesn't free {}", disk.to_free(&fs));
+        return;
+    };
+    if plan.strategy != strategy {
+        println!("Too many ways of combining entries to search, deleting the smallest directory");
+    }
+    for entry in &plan.deletions {
+        println!("Delete {} ({})", fs.entry_path(entry), fs.entry_size(entry));
+    }
+    println!("Frees {} of {} needed", plan.freed, plan.to_free);
 }
 
 fn part_one(input: &Input) -> usize {
-    let dirs = construct_fs(input);
-    dirs.values()
-        .map(|d| dir_size(d, &dirs))
+    let fs = construct_fs(input);
+    fs.iter()
+        .map(|(id, _)| fs.size(id))
         .filter(|s| *s <= 100_000)
         .sum()
 }
 
 fn part_two(input: &Input) -> usize {
-    let dirs = construct_fs(input);
-    let disk: usize = 70_000_000;
-    let needed: usize = 30_000_000;
-    let root_size = dir_size(dirs.get(&Path::new()).unwrap(), &dirs);
-    let free = disk - root_size;
-    let to_free = needed - free;
-
-    dirs.values()
-        .map(|d| dir_size(d, &dirs))
-        .filter(|s| *s >= to_free)
-        .min()
+    let fs = construct_fs(input);
+    plan_deletion(&fs, &Disk::PUZZLE, DeletionStrategy::SmallestDirectory)
         .unwrap()
+        .freed
+}
+
+/// Each command with the transcript line it was on.
+type Input = Vec<(usize, Command)>;
+
+/// Split a path like `../a/b` or `/x/y` into steps.
+fn parse_path(path: &str) -> Vec<CdArg> {
+    let root = path.starts_with('/').then_some(CdArg::Root);
+    root.into_iter()
+        .chain(
+            path.split('/')
+                .filter(|name| !name.is_empty() && *name != ".")
+                .map(|name| match name {
+                    ".." => CdArg::Up,
+                    name => CdArg::Into(String::from(name)),
+                }),
+        )
+        .collect()
 }
 
-type Input = Vec<Command>;
+fn parse_file_info(entry: &str) -> Option<FileInfo> {
+    let (first, name) = entry.split_once(' ')?;
+    if first == "dir" {
+        Some(FileInfo::Directory(String::from(name)))
+    } else {
+        Some(FileInfo::File(File {
+            name: String::from(name),
+            size: first.parse().ok()?,
+        }))
+    }
+}
+
+/// Parse a transcript a line at a time, where lines starting with `$ ` are
+/// commands and any other lines are the output of the last one. Only the
+/// output of `ls` is kept.
 fn parse_input(input_str: &str) -> Input {
-    input_str
-        .split("$ ")
-        .skip(1)
-        .map(|line| {
-            let line = line.trim_start_matches("\n");
-            let line = line.trim_end_matches("\n");
-            if line.starts_with("cd") {
-                let arg = &line[3..];
-                match arg {
-                    ".." => Command::Cd(CdArg::Up),
-                    "/" => Command::Cd(CdArg::Root),
-                    name => Command::Cd(CdArg::Into(String::from(name))),
-                }
-            } else if line.starts_with("ls") {
-                Command::Ls(
-                    line[3..]
-                        .lines()
-                        .map(|entry| {
-                            if entry.starts_with("dir") {
-                                FileInfo::Directory(String::from(&entry[4..]))
-                            } else {
-                                let (size, name) = entry.split_once(" ").unwrap();
-                                FileInfo::File(File {
-                                    name: String::from(name),
-                                    size: size.parse().unwrap(),
-                                })
-                            }
-                        })
-                        .collect(),
+    let mut input: Input = Vec::new();
+    for (i, line) in input_str.lines().enumerate() {
+        let line_number = i + 1;
+        if let Some(command_line) = line.strip_prefix("$ ") {
+            // Everything after the command name is a single path, which may
+            // contain spaces.
+            let (name, path) = match command_line.split_once(' ') {
+                Some((name, path)) => (name, Some(path)),
+                None => (command_line, None),
+            };
+            let command = match (name, path) {
+                ("cd", Some(path)) => Command::Cd(parse_path(path)),
+                ("ls", None) => Command::Ls(Vec::new(), Vec::new()),
+                ("ls", Some(path)) => Command::Ls(parse_path(path), Vec::new()),
+                ("pwd", None) => Command::Pwd,
+                _ => Command::Unknown(String::from(command_line)),
+            };
+            input.push((line_number, command));
+        } else if let Some((_, Command::Ls(_, file_infos))) = input.last_mut() {
+            if line.is_empty() {
+                continue;
+            }
+            let file_info = parse_file_info(line)
+                .unwrap_or_else(|| panic!("Line {}: invalid listing {:?}", line_number, line));
+            file_infos.push((line_number, file_info));
+        }
+    }
+    input
+}
+
+/// Format a size in bytes with binary units like `du -h`, keeping one
+/// decimal place for small values.
+fn human_size(size: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}{}", size, UNITS[0])
+    } else if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+/// Draw the filesystem like `tree`, with entries in name order and the
+/// total size of each directory.
+fn render_tree(fs: &FileSystem) -> String {
+    fn visit(fs: &FileSystem, id: NodeId, prefix: &str, out: &mut String) {
+        let dir = fs.dir(id);
+        let mut entries: Vec<(&str, String, Option<NodeId>)> = dir
+            .dirs
+            .iter()
+            .map(|child| {
+                let child_dir = fs.dir(*child);
+                let line = format!("{}/ ({})", child_dir.name, human_size(child_dir.size));
+                (child_dir.name.as_str(), line, Some(*child))
+            })
+            .chain(dir.files.iter().map(|file| {
+                let line = format!("{} {}", file.name, human_size(file.size));
+                (file.name.as_str(), line, None)
+            }))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (i, (_, line, child)) in entries.iter().enumerate() {
+            let last = i + 1 == entries.len();
+            let branch = if last { "└── " } else { "├── " };
+            out.push_str(&format!("{}{}{}\n", prefix, branch, line));
+            if let Some(child) = child {
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                visit(fs, *child, &prefix, out);
+            }
+        }
+    }
+
+    let mut out = format!("/ ({})\n", human_size(fs.size(FileSystem::ROOT)));
+    visit(fs, FileSystem::ROOT, "", &mut out);
+    out
+}
+
+/// The paths and sizes of the largest directories, biggest first.
+fn largest_dirs(fs: &FileSystem, count: usize) -> Vec<(String, usize)> {
+    let mut dirs: Vec<(String, usize)> = fs
+        .iter()
+        .map(|(id, _)| (fs.path(id), fs.size(id)))
+        .collect();
+    dirs.sort_by_key(|(path, size)| (Reverse(*size), path.clone()));
+    dirs.truncate(count);
+    dirs
+}
+
+fn print_du(input: &Input, count: usize) {
+    for (path, size) in largest_dirs(&construct_fs(input), count) {
+        println!("{}\t{}", human_size(size), path);
+    }
+}
+
+/// Refuse names which would point outside of their directory.
+fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Can't export an entry named {:?}", name),
+        ));
+    }
+    Ok(())
+}
+
+/// Recreate the filesystem under a directory on disk, with sparse files of
+/// the recorded sizes.
+fn export_to_dir(fs: &FileSystem, target: &Path) -> io::Result<()> {
+    for (id, dir) in fs.iter() {
+        if id != FileSystem::ROOT {
+            check_name(&dir.name)?;
+        }
+        let path = target.join(fs.path(id).trim_start_matches('/'));
+        std::fs::create_dir_all(&path)?;
+        for file in &dir.files {
+            check_name(&file.name)?;
+            std::fs::File::create(path.join(&file.name))?.set_len(file.size as u64)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write one `ustar` header block.
+fn write_tar_header(out: &mut impl Write, path: &str, size: usize, is_dir: bool) -> io::Result<()> {
+    // Paths too long for the name field are split at a `/` into a prefix.
+    let (prefix, name) = if path.len() <= 100 {
+        ("", path)
+    } else {
+        let split = path
+            .rmatch_indices('/')
+            .map(|(split, _)| split)
+            .find(|split| *split <= 155 && path.len() - split - 1 <= 100)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Path too long for tar: {}", path),
                 )
+            })?;
+        (&path[..split], &path[split + 1..])
+    };
+
+    let mut header = [0u8; 512];
+    let mut field =
+        |offset: usize, value: &[u8]| header[offset..offset + value.len()].copy_from_slice(value);
+    field(0, name.as_bytes());
+    field(100, if is_dir { b"0000755" } else { b"0000644" });
+    field(108, b"0000000");
+    field(116, b"0000000");
+    // Sizes too big for 11 octal digits are stored in binary, as GNU tar does.
+    if (size as u64) < 1 << 33 {
+        field(124, format!("{:011o}", size).as_bytes());
+    } else {
+        field(124, &[0x80, 0, 0, 0]);
+        field(128, &(size as u64).to_be_bytes());
+    }
+    field(136, b"00000000000");
+    field(148, b"        ");
+    field(156, if is_dir { b"5" } else { b"0" });
+    field(257, b"ustar\0");
+    field(263, b"00");
+    field(345, prefix.as_bytes());
+    let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+    header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+    out.write_all(&header)
+}
+
+/// Write the filesystem as a tar archive of zero-filled files.
+fn write_tar(fs: &FileSystem, out: &mut impl Write) -> io::Result<()> {
+    for (id, dir) in fs.iter() {
+        if id != FileSystem::ROOT {
+            check_name(&dir.name)?;
+            write_tar_header(out, &format!("{}/", &fs.path(id)[1..]), 0, true)?;
+        }
+        for file in &dir.files {
+            check_name(&file.name)?;
+            write_tar_header(out, &fs.child_path(id, &file.name)[1..], file.size, false)?;
+            let padding = (512 - file.size % 512) % 512;
+            io::copy(&mut io::repeat(0).take((file.size + padding) as u64), out)?;
+        }
+    }
+    out.write_all(&[0; 1024])
+}
+
+/// Write a transcript of exploring a local directory, which `parse_input`
+/// reads back as the same tree.
+fn transcript_of_dir(root: &Path) -> io::Result<String> {
+    fn visit(dir: &Path, out: &mut String) -> io::Result<()> {
+        let mut entries = std::fs::read_dir(dir)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok((
+                    entry.file_name().to_string_lossy().into_owned(),
+                    entry.metadata()?,
+                ))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.retain(|(_, metadata)| metadata.is_dir() || metadata.is_file());
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        out.push_str("$ ls\n");
+        for (name, metadata) in &entries {
+            if metadata.is_dir() {
+                out.push_str(&format!("dir {}\n", name));
             } else {
-                panic!("Unsupported command")
+                out.push_str(&format!("{} {}\n", metadata.len(), name));
             }
-        })
-        .collect()
+        }
+        for (name, _) in entries.iter().filter(|(_, metadata)| metadata.is_dir()) {
+            out.push_str(&format!("$ cd {}\n", name));
+            visit(&dir.join(name), out)?;
+            out.push_str("$ cd ..\n");
+        }
+        Ok(())
+    }
+
+    let mut out = String::from("$ cd /\n");
+    visit(root, &mut out)?;
+    Ok(out)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Query {
+    /// Files and directories with names matching a glob of `*` and `?`.
+    Name(String),
+    Extension(String),
+    FilesLargerThan(usize),
+    /// Directories with sizes in an inclusive range.
+    DirectoriesSized(usize, usize),
+    /// The files or directories furthest from the root.
+    Deepest,
+    /// The largest file in each directory which has any.
+    LargestFiles,
+}
+
+impl Query {
+    /// Read a query from words like `name *.txt` or `dirs 100 2000`.
+    fn parse(words: &[&str]) -> Option<Query> {
+        match words {
+            ["name", glob] => Some(Query::Name(String::from(*glob))),
+            ["ext", extension] => Some(Query::Extension(String::from(
+                extension.trim_start_matches('.'),
+            ))),
+            ["larger", size] => Some(Query::FilesLargerThan(size.parse().ok()?)),
+            ["dirs", min, max] => Some(Query::DirectoriesSized(
+                min.parse().ok()?,
+                max.parse().ok()?,
+            )),
+            ["deepest"] => Some(Query::Deepest),
+            ["largest"] => Some(Query::LargestFiles),
+            _ => None,
+        }
+    }
+}
+
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and where in the name it's matched up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the `*` take one more character and try again.
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Every entry in the filesystem other than the root, in the order they
+/// were added.
+fn entries(fs: &FileSystem) -> impl Iterator<Item = Entry> + '_ {
+    fs.iter().flat_map(|(id, dir)| {
+        let this = (id != FileSystem::ROOT).then_some(Entry::Directory(id));
+        this.into_iter()
+            .chain((0..dir.files.len()).map(move |i| Entry::File(id, i)))
+    })
+}
+
+fn run_query(fs: &FileSystem, query: &Query) -> Vec<Entry> {
+    let name = |entry: &Entry| match entry {
+        Entry::Directory(id) => fs.dir(*id).name.as_str(),
+        Entry::File(id, i) => fs.dir(*id).files[*i].name.as_str(),
+    };
+    match query {
+        Query::Name(glob) => entries(fs)
+            .filter(|entry| glob_match(glob, name(entry)))
+            .collect(),
+        Query::Extension(extension) => entries(fs)
+            .filter(|entry| {
+                matches!(entry, Entry::File(..))
+                    && name(entry)
+                        .rsplit_once('.')
+                        .is_some_and(|(_, ext)| ext == extension)
+            })
+            .collect(),
+        Query::FilesLargerThan(size) => entries(fs)
+            .filter(|entry| matches!(entry, Entry::File(..)) && fs.entry_size(entry) > *size)
+            .collect(),
+        Query::DirectoriesSized(min, max) => fs
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| (*min..=*max).contains(&fs.size(*id)))
+            .map(Entry::Directory)
+            .collect(),
+        Query::Deepest => {
+            let depth = |entry: &Entry| match entry {
+                Entry::Directory(id) => fs.depth(*id),
+                Entry::File(id, _) => fs.depth(*id) + 1,
+            };
+            let deepest = entries(fs).map(|entry| depth(&entry)).max();
+            entries(fs)
+                .filter(|entry| Some(depth(entry)) == deepest)
+                .collect()
+        }
+        Query::LargestFiles => fs
+            .iter()
+            .filter_map(|(id, dir)| {
+                // Reversed, as ties go to the last one found.
+                let largest = (0..dir.files.len())
+                    .rev()
+                    .max_by_key(|i| dir.files[*i].size)?;
+                Some(Entry::File(id, largest))
+            })
+            .collect(),
+    }
+}
+
+fn print_query(input: &Input, words: &[&str]) {
+    let Some(query) = Query::parse(words) else {
+        println!("Expected a query: name <glob>, ext <extension>, larger <size>, dirs <min> <max>, deepest or largest");
+        return;
+    };
+    let fs = construct_fs(input);
+    for entry in run_query(&fs, &query) {
+        println!("{}\t{}", fs.entry_size(&entry), fs.entry_path(&entry));
+    }
+}
+
+/// Print the size of the directory at a path like `/a/e`.
+fn print_size(input: &Input, path: &str) {
+    let fs = construct_fs(input);
+    let names: Vec<&str> = path.split('/').filter(|name| !name.is_empty()).collect();
+    match fs.lookup(&names) {
+        Some(id) => println!("{} {}", fs.path(id), fs.size(id)),
+        None => println!("No directory at {}", path),
+    }
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    // These write a transcript or archive to stdout, so skip the usual output.
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["transcript", dir] => {
+            print!(
+                "{}",
+                transcript_of_dir(Path::new(dir)).expect("Couldn't read directory")
+            );
+            return;
+        }
+        ["tar"] => {
+            let input = parse_input(&get_day_input("07"));
+            write_tar(&construct_fs(&input), &mut io::stdout().lock())
+                .expect("Couldn't write archive");
+            return;
+        }
+        _ => {}
+    }
+
     let input_str = get_day_input("07");
     let input = parse_input(&input_str);
     println!("Day 07:");
     println!("=========");
     println!("Part one: {}", print_elapsed_time(|| part_one(&input)));
     println!("Part two: {}", print_elapsed_time(|| part_two(&input)));
+
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["size", path] => print_size(&input, path),
+        ["check"] => print_issues(&input),
+        ["export", dir] => {
+            export_to_dir(&construct_fs(&input), Path::new(dir)).expect("Couldn't export");
+        }
+        ["tree"] => print!("{}", render_tree(&construct_fs(&input))),
+        ["du"] => print_du(&input, 10),
+        ["plan", strategy] => print_plan(&input, strategy, &Disk::PUZZLE),
+        ["plan", strategy, size, needed] => {
+            let disk = Disk {
+                size: size.parse().expect("Invalid disk size"),
+                needed: needed.parse().expect("Invalid needed space"),
+            };
+            print_plan(&input, strategy, &disk);
+        }
+        ["find", query @ ..] => print_query(&input, query),
+        ["du", count] => print_du(&input, count.parse().expect("Invalid count")),
+        _ => {}
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k";
+
     #[test]
     fn test_given_example_07() {
         let input_str: String = String::from(
@@ -223,4 +1259,416 @@ $ ls
         assert_eq!(part_one(&input), 95437);
         assert_eq!(part_two(&input), 24933642);
     }
+
+    #[test]
+    fn test_file_system_07() {
+        let fs = construct_fs(&parse_input(EXAMPLE));
+
+        let e = fs.lookup(&["a", "e"]).unwrap();
+        assert_eq!(fs.path(e), "/a/e");
+        assert_eq!(fs.size(e), 584);
+        assert_eq!(fs.size(fs.lookup(&["a"]).unwrap()), 94853);
+        assert_eq!(fs.size(fs.lookup(&["d"]).unwrap()), 24933642);
+        assert_eq!(fs.size(FileSystem::ROOT), 48381165);
+        assert_eq!(fs.path(FileSystem::ROOT), "/");
+        assert_eq!(fs.lookup(&["e"]), None);
+        assert_eq!(fs.iter().count(), 4);
+    }
+
+    #[test]
+    fn test_rendering_07() {
+        assert_eq!(human_size(584), "584B");
+        assert_eq!(human_size(2557), "2.5K");
+        assert_eq!(human_size(94853), "93K");
+        assert_eq!(human_size(48381165), "46M");
+
+        let fs = construct_fs(&parse_input(
+            "$ cd /\n$ ls\ndir a\n14848514 b.txt\n$ cd a\n$ ls\ndir e\n29116 f\n$ cd e\n$ ls\n584 i",
+        ));
+        assert_eq!(
+            render_tree(&fs),
+            "/ (14M)
+├── a/ (29K)
+│   ├── e/ (584B)
+│   │   └── i 584B
+│   └── f 28K
+└── b.txt 14M
+"
+        );
+        assert_eq!(
+            largest_dirs(&fs, 2),
+            vec![(String::from("/"), 14878214), (String::from("/a"), 29700)]
+        );
+    }
+
+    #[test]
+    fn test_check_transcript_07() {
+        let input = parse_input(
+            "$ cd /
+$ ls
+dir a
+14848514 b.txt
+$ cd ..
+$ cd a
+$ ls
+29116 f
+$ cd /
+$ ls
+dir a
+14848514 b.txt
+100 b.txt
+$ ls
+
+2 b.txt
+$ cd e
+$ ls
+584 i",
+        );
+
+        let (fs, issues) = replay(&input);
+
+        assert_eq!(
+            issues.iter().map(Issue::to_string).collect::<Vec<_>>(),
+            vec![
+                "Line 5: moved up from the root",
+                "Line 10: / was already listed on line 2",
+                "Line 13: /b.txt was listed with size 14848514, now 100",
+                "Line 14: / was already listed on line 10",
+                "Line 16: /b.txt was listed with size 14848514, now 2",
+                "Line 17: moved into /e, which was never listed",
+            ]
+        );
+        assert_eq!(fs.size(FileSystem::ROOT), 14848514 + 29116 + 584);
+        assert!(replay(&parse_input(EXAMPLE)).1.is_empty());
+    }
+
+    #[test]
+    fn test_shell_dialect_07() {
+        let input = parse_input(
+            "$ cd /
+$ ls
+dir a
+10 $ odd name
+$ ls a
+dir b
+$ cd a/b
+$ pwd
+/a/b
+$ ls
+5 c
+$ cd /a/../a/./b/../..
+$ rm -rf /
+$ ls ../..",
+        );
+
+        assert_eq!(input[3], (7, Command::Cd(parse_path("a/b"))));
+        assert_eq!(
+            input[2].1,
+            Command::Ls(
+                vec![CdArg::Into(String::from("a"))],
+                vec![(6, FileInfo::Directory(String::from("b")))]
+            )
+        );
+        assert_eq!(
+            parse_path("/a/../b"),
+            vec![
+                CdArg::Root,
+                CdArg::Into(String::from("a")),
+                CdArg::Up,
+                CdArg::Into(String::from("b"))
+            ]
+        );
+
+        let (fs, issues) = replay(&input);
+
+        assert_eq!(fs.lookup(&["$ odd name"]), None);
+        assert_eq!(fs.dir(FileSystem::ROOT).files[0].name, "$ odd name");
+        assert_eq!(fs.size(fs.lookup(&["a"]).unwrap()), 5);
+        assert_eq!(fs.size(FileSystem::ROOT), 15);
+        assert_eq!(
+            issues.iter().map(Issue::to_string).collect::<Vec<_>>(),
+            vec![
+                "Line 13: unknown command \"rm -rf /\"",
+                "Line 14: moved up from the root",
+                "Line 14: moved up from the root",
+                "Line 14: / was already listed on line 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_names_with_spaces_07() {
+        let input = parse_input(
+            "$ cd /
+$ ls
+dir My Docs
+$ cd My Docs
+$ ls
+dir old stuff
+12 a b.txt
+$ ls /My Docs/old stuff
+3 c
+$ cd ..
+$ ls My Docs/old stuff
+3 c",
+        );
+
+        assert_eq!(
+            input[2].1,
+            Command::Cd(vec![CdArg::Into(String::from("My Docs"))])
+        );
+        let (fs, issues) = replay(&input);
+        assert_eq!(
+            issues.iter().map(Issue::to_string).collect::<Vec<_>>(),
+            vec!["Line 11: /My Docs/old stuff was already listed on line 8"]
+        );
+        assert_eq!(fs.size(fs.lookup(&["My Docs"]).unwrap()), 15);
+        assert_eq!(fs.dir(FileSystem::ROOT).dirs.len(), 1);
+    }
+
+    #[test]
+    fn test_plan_deletion_07() {
+        let fs = construct_fs(&parse_input(EXAMPLE));
+        let plan = |strategy| plan_deletion(&fs, &Disk::PUZZLE, strategy).unwrap();
+
+        assert_eq!(plan(DeletionStrategy::SmallestDirectory).freed, 24933642);
+        assert_eq!(plan(DeletionStrategy::Directories).freed, 24933642);
+
+        let disk = Disk {
+            size: 48_381_165,
+            needed: 31_000,
+        };
+        let plan = |strategy| plan_deletion(&fs, &disk, strategy).unwrap();
+        assert_eq!(plan(DeletionStrategy::SmallestDirectory).freed, 94853);
+        assert_eq!(plan(DeletionStrategy::Directories).freed, 94853);
+        let a = fs.lookup(&["a"]).unwrap();
+        assert_eq!(
+            plan(DeletionStrategy::Entries),
+            Plan {
+                deletions: vec![Entry::File(a, 0), Entry::File(a, 1)],
+                freed: 31673,
+                to_free: 31000,
+                strategy: DeletionStrategy::Entries
+            }
+        );
+
+        let disk = Disk {
+            size: 48_381_165,
+            needed: 48_381_166,
+        };
+        assert_eq!(plan_deletion(&fs, &disk, DeletionStrategy::Entries), None);
+    }
+
+    #[test]
+    fn test_plan_deletion_matches_brute_force_07() {
+        let mut rng = aoc2022::Rng::new(7);
+        // Scaled up, the totals are too spread out for a bitset.
+        for scale in (0..200).map(|i| if i % 2 == 0 { 1 } else { 1 << 30 }) {
+            let mut fs = FileSystem::new();
+            for i in 0..rng.below(4) {
+                let parent = NodeId(rng.below(fs.dirs.len() as u64) as usize);
+                fs.add_dir(parent, &i.to_string());
+            }
+            for i in 0..1 + rng.below(5) {
+                let dir = NodeId(rng.below(fs.dirs.len() as u64) as usize);
+                let size = (1 + rng.below(100) as usize) * scale;
+                fs.add_file(
+                    dir,
+                    File {
+                        name: i.to_string(),
+                        size,
+                    },
+                );
+            }
+            fs.compute_sizes();
+            let disk = Disk {
+                size: fs.size(FileSystem::ROOT),
+                needed: rng.below(fs.size(FileSystem::ROOT) as u64 + 1) as usize,
+            };
+
+            for with_files in [false, true] {
+                let candidates = candidates(&fs, with_files);
+                let inside = |a: usize, b: usize| a <= b && b < candidates[a].end;
+                let mut best: Option<usize> = None;
+                for subset in 0..1u32 << candidates.len() {
+                    let chosen: Vec<usize> = (0..candidates.len())
+                        .filter(|i| subset & 1 << i != 0)
+                        .collect();
+                    let nested = chosen
+                        .iter()
+                        .any(|a| chosen.iter().any(|b| a != b && inside(*a, *b)));
+                    let total: usize = chosen.iter().map(|i| candidates[*i].size).sum();
+                    if !nested && total >= disk.to_free(&fs) && best.is_none_or(|best| total < best)
+                    {
+                        best = Some(total);
+                    }
+                }
+
+                let strategy = if with_files {
+                    DeletionStrategy::Entries
+                } else {
+                    DeletionStrategy::Directories
+                };
+                let plan = plan_deletion(&fs, &disk, strategy).unwrap();
+                assert_eq!(plan.strategy, strategy);
+                assert_eq!(Some(plan.freed), best);
+                let chosen: Vec<usize> = plan
+                    .deletions
+                    .iter()
+                    .map(|d| candidates.iter().position(|c| c.entry == *d).unwrap())
+                    .collect();
+                assert!(!chosen
+                    .iter()
+                    .any(|a| chosen.iter().any(|b| a != b && inside(*a, *b))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_deletion_gives_up_07() {
+        let mut rng = aoc2022::Rng::new(48);
+        let mut spread_out = |files: usize| {
+            let mut fs = FileSystem::new();
+            for i in 0..files {
+                let size = 100_000_000_000 + rng.below(900_000_000_000) as usize;
+                fs.add_file(
+                    FileSystem::ROOT,
+                    File {
+                        name: i.to_string(),
+                        size,
+                    },
+                );
+            }
+            fs.compute_sizes();
+            let disk = Disk {
+                size: fs.size(FileSystem::ROOT),
+                needed: fs.size(FileSystem::ROOT) / 2,
+            };
+            plan_deletion(&fs, &disk, DeletionStrategy::Entries).unwrap()
+        };
+
+        let plan = spread_out(12);
+        assert_eq!(plan.strategy, DeletionStrategy::Entries);
+        assert!(plan.deletions.len() > 1);
+
+        // Too spread out for a bitset, with almost every subset adding up to
+        // something different.
+        let plan = spread_out(32);
+        assert_eq!(plan.strategy, DeletionStrategy::SmallestDirectory);
+        assert_eq!(plan.deletions, vec![Entry::Directory(FileSystem::ROOT)]);
+    }
+
+    #[test]
+    fn test_tar_07() {
+        let fs = construct_fs(&parse_input(
+            "$ cd /\n$ ls\ndir a\n3 b\n$ cd a\n$ ls\n600 c",
+        ));
+        let mut tar = Vec::new();
+        write_tar(&fs, &mut tar).unwrap();
+
+        assert_eq!(tar.len(), 3 * 512 + 512 + 1024 + 1024);
+        let headers: Vec<&[u8]> = [0, 1024, 1536].iter().map(|i| &tar[*i..i + 512]).collect();
+        assert_eq!(&headers[0][..2], b"b\0");
+        assert_eq!(&headers[1][..3], b"a/\0");
+        assert_eq!(headers[1][156], b'5');
+        assert_eq!(&headers[2][..4], b"a/c\0");
+        assert_eq!(&headers[2][124..136], b"00000001130\0");
+        for header in headers {
+            let checksum: u32 = header
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    if (148..156).contains(&i) {
+                        b' ' as u32
+                    } else {
+                        *b as u32
+                    }
+                })
+                .sum();
+            assert_eq!(&header[148..156], format!("{:06o}\0 ", checksum).as_bytes());
+        }
+        assert!(tar[2048..].iter().all(|b| *b == 0));
+
+        let mut header = Vec::new();
+        let long = format!("{}/{}", "x".repeat(120), "y".repeat(90));
+        write_tar_header(&mut header, &long, 0, false).unwrap();
+        assert_eq!(&header[..90], "y".repeat(90).as_bytes());
+        assert_eq!(&header[345..465], "x".repeat(120).as_bytes());
+        assert!(write_tar_header(&mut Vec::new(), &"z".repeat(101), 0, false).is_err());
+
+        let mut header = Vec::new();
+        let long = format!("{}/{}", "é".repeat(70), "y".repeat(90));
+        write_tar_header(&mut header, &long, 0, false).unwrap();
+        assert_eq!(&header[345..485], "é".repeat(70).as_bytes());
+        assert!(write_tar_header(
+            &mut Vec::new(),
+            &format!("a{}/{}", "é".repeat(78), "y".repeat(90)),
+            0,
+            false
+        )
+        .is_err());
+
+        let mut header = Vec::new();
+        write_tar_header(&mut header, "big", 10 << 30, false).unwrap();
+        assert_eq!(header[124], 0x80);
+        assert_eq!(&header[128..136], &(10u64 << 30).to_be_bytes());
+
+        let fs = construct_fs(&parse_input("$ cd /\n$ ls\ndir ..\n$ cd ..\n$ ls\n1 x"));
+        assert!(write_tar(&fs, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_export_round_trip_07() {
+        let spaced =
+            "$ cd /\n$ ls\ndir My Docs\n$ cd My Docs\n$ ls\ndir inner\n$ cd inner\n$ ls\n7 f g.txt";
+        for (i, transcript) in [EXAMPLE, spaced].iter().enumerate() {
+            let fs = construct_fs(&parse_input(transcript));
+            let target = env::temp_dir().join(format!("aoc2022-07-{}-{}", std::process::id(), i));
+            export_to_dir(&fs, &target).unwrap();
+            let transcript = transcript_of_dir(&target);
+            std::fs::remove_dir_all(&target).unwrap();
+
+            let (exported, issues) = replay(&parse_input(&transcript.unwrap()));
+            assert!(issues.is_empty());
+            assert_eq!(render_tree(&exported), render_tree(&fs));
+        }
+    }
+
+    #[test]
+    fn test_glob_match_07() {
+        assert!(glob_match("*.txt", "b.txt"));
+        assert!(glob_match("d.*", "d.log"));
+        assert!(glob_match("?", "k"));
+        assert!(glob_match("*a*b*", "xxaxxbb"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("*.txt", "b.txt.gz"));
+        assert!(!glob_match("??", "k"));
+        assert!(!glob_match("a*c", "abcb"));
+    }
+
+    #[test]
+    fn test_queries_07() {
+        let fs = construct_fs(&parse_input(EXAMPLE));
+        let find = |words: &[&str]| {
+            run_query(&fs, &Query::parse(words).unwrap())
+                .iter()
+                .map(|entry| fs.entry_path(entry))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(find(&["name", "d*"]), vec!["/d", "/d/d.log", "/d/d.ext"]);
+        assert_eq!(find(&["ext", ".lst"]), vec!["/a/h.lst"]);
+        assert_eq!(
+            find(&["larger", "8000000"]),
+            vec!["/b.txt", "/c.dat", "/d/d.log"]
+        );
+        assert_eq!(find(&["dirs", "500", "100000"]), vec!["/a", "/a/e"]);
+        assert_eq!(find(&["deepest"]), vec!["/a/e/i"]);
+        assert_eq!(
+            find(&["largest"]),
+            vec!["/b.txt", "/a/h.lst", "/d/d.log", "/a/e/i"]
+        );
+        assert_eq!(Query::parse(&["larger", "lots"]), None);
+        assert_eq!(fs.depth(fs.lookup(&["a", "e"]).unwrap()), 2);
+    }
 }
@@ -0,0 +1,234 @@
+//! Inclusive intervals of integers, with constant-time set operations.
+
+use std::cmp::{max, min};
+use std::fmt;
+
+/// The integers from `start` to `end` inclusive, which is never empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval {
+    start: u64,
+    end: u64,
+}
+
+impl Interval {
+    pub fn new(start: u64, end: u64) -> Self {
+        assert!(
+            start <= end,
+            "Interval start {} is after end {}",
+            start,
+            end
+        );
+        Interval { start, end }
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// The number of integers in the interval, which is wider than a `u64`
+    /// so the interval of every `u64` fits.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u128 {
+        (self.end - self.start) as u128 + 1
+    }
+
+    /// Whether every integer in `other` is also in this interval.
+    pub fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn contains_point(&self, point: u64) -> bool {
+        self.start <= point && point <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        if self.overlaps(other) {
+            Some(Interval::new(
+                max(self.start, other.start),
+                min(self.end, other.end),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// The single interval covering both, if they overlap or are adjacent.
+    pub fn union(&self, other: &Interval) -> Option<Interval> {
+        if self.start <= other.end.saturating_add(1) && other.start <= self.end.saturating_add(1) {
+            Some(Interval::new(
+                min(self.start, other.start),
+                max(self.end, other.end),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// A set of integers stored as sorted, disjoint and non-adjacent intervals.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        IntervalSet::default()
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    /// The number of integers in the set.
+    pub fn len(&self) -> u128 {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        // Everything from `first` up to `last` touches the new interval and
+        // is merged into it.
+        let first = self
+            .intervals
+            .partition_point(|i| i.end.saturating_add(1) < interval.start);
+        let last = self
+            .intervals
+            .partition_point(|i| i.start <= interval.end.saturating_add(1));
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |acc, i| acc.union(i).unwrap());
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn remove(&mut self, interval: Interval) {
+        let first = self.intervals.partition_point(|i| i.end < interval.start);
+        let last = self.intervals.partition_point(|i| i.start <= interval.end);
+        let mut remaining = Vec::new();
+        for i in &self.intervals[first..last] {
+            if i.start < interval.start {
+                remaining.push(Interval::new(i.start, interval.start - 1));
+            }
+            if i.end > interval.end {
+                remaining.push(Interval::new(interval.end + 1, i.end));
+            }
+        }
+        self.intervals.splice(first..last, remaining);
+    }
+
+    /// The interval in the set containing the point, if there is one.
+    pub fn query(&self, point: u64) -> Option<&Interval> {
+        let index = self.intervals.partition_point(|i| i.end < point);
+        self.intervals
+            .get(index)
+            .filter(|i| i.contains_point(point))
+    }
+
+    pub fn contains(&self, interval: &Interval) -> bool {
+        self.query(interval.start)
+            .is_some_and(|i| i.contains(interval))
+    }
+
+    /// The integers between the lowest and highest in the set which are not
+    /// in it.
+    pub fn gaps(&self) -> IntervalSet {
+        IntervalSet {
+            intervals: self
+                .intervals
+                .windows(2)
+                .map(|pair| Interval::new(pair[0].end + 1, pair[1].start - 1))
+                .collect(),
+        }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_operations() {
+        let a = Interval::new(2, 8);
+        let b = Interval::new(3, 7);
+        let c = Interval::new(9, 4_000_000_000);
+
+        assert_eq!(a.len(), 7);
+        assert_eq!(c.len(), 3_999_999_992);
+        assert_eq!(Interval::new(0, u64::MAX).len(), 1 << 64);
+        assert!(a.contains(&b) && !b.contains(&a));
+        assert!(a.overlaps(&b) && !a.overlaps(&c));
+        assert!(a.contains_point(8) && !a.contains_point(9));
+        assert_eq!(a.intersection(&b), Some(b));
+        assert_eq!(a.intersection(&c), None);
+        assert_eq!(a.union(&c), Some(Interval::new(2, 4_000_000_000)));
+        assert_eq!(b.union(&c), None);
+    }
+
+    #[test]
+    fn test_interval_set() {
+        let mut set: IntervalSet = [
+            Interval::new(10, 20),
+            Interval::new(1, 3),
+            Interval::new(4, 5),
+            Interval::new(30, 40),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            set.intervals(),
+            &[
+                Interval::new(1, 5),
+                Interval::new(10, 20),
+                Interval::new(30, 40)
+            ]
+        );
+        assert_eq!(set.len(), 27);
+        assert_eq!(set.query(15), Some(&Interval::new(10, 20)));
+        assert_eq!(set.query(25), None);
+        assert!(set.contains(&Interval::new(12, 20)));
+        assert!(!set.contains(&Interval::new(5, 10)));
+        assert_eq!(
+            set.gaps().intervals(),
+            &[Interval::new(6, 9), Interval::new(21, 29)]
+        );
+
+        set.insert(Interval::new(6, 30));
+        assert_eq!(set.intervals(), &[Interval::new(1, 40)]);
+
+        set.remove(Interval::new(5, 35));
+        assert_eq!(
+            set.intervals(),
+            &[Interval::new(1, 4), Interval::new(36, 40)]
+        );
+        set.remove(Interval::new(1, 40));
+        assert!(set.is_empty());
+    }
+}
@@ -7,6 +7,9 @@ use std::fs;
 use std::str::FromStr;
 use std::time::Instant;
 
+pub mod interval;
+pub mod rope;
+
 /// Time a closure in microseconds and print the results.
 pub fn print_elapsed_time<T, F>(function: F) -> T
 where
@@ -49,3 +52,29 @@ where
 {
     parse_input_with(input, str::lines)
 }
+
+/// A small seedable pseudo-random number generator (SplitMix64), for
+/// reproducible simulations and generated inputs without external crates.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a number in `0..bound`.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
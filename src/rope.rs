@@ -0,0 +1,244 @@
+//! A sequence supporting splitting, joining and reversing in logarithmic time.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Rng;
+
+type Link<T> = Option<Box<Node<T>>>;
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    value: T,
+    priority: u64,
+    size: usize,
+    /// Whether the children of this node still need to be reversed.
+    reversed: bool,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+fn size<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+fn next_priority() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    Rng::new(COUNTER.fetch_add(1, Ordering::Relaxed)).next_u64()
+}
+
+impl<T> Node<T> {
+    fn new(value: T) -> Self {
+        Node {
+            value,
+            priority: next_priority(),
+            size: 1,
+            reversed: false,
+            left: None,
+            right: None,
+        }
+    }
+
+    fn update(&mut self) {
+        self.size = size(&self.left) + 1 + size(&self.right);
+    }
+
+    /// Apply a pending reversal to the children, deferring it to theirs.
+    fn push_down(&mut self) {
+        if self.reversed {
+            std::mem::swap(&mut self.left, &mut self.right);
+            for child in [&mut self.left, &mut self.right].into_iter().flatten() {
+                child.reversed = !child.reversed;
+            }
+            self.reversed = false;
+        }
+    }
+}
+
+/// Split off the first `at` elements.
+fn split<T>(link: Link<T>, at: usize) -> (Link<T>, Link<T>) {
+    match link {
+        None => (None, None),
+        Some(mut node) => {
+            node.push_down();
+            let left_size = size(&node.left);
+            if at <= left_size {
+                let (first, second) = split(node.left.take(), at);
+                node.left = second;
+                node.update();
+                (first, Some(node))
+            } else {
+                let (first, second) = split(node.right.take(), at - left_size - 1);
+                node.right = first;
+                node.update();
+                (Some(node), second)
+            }
+        }
+    }
+}
+
+fn merge<T>(first: Link<T>, second: Link<T>) -> Link<T> {
+    match (first, second) {
+        (None, link) | (link, None) => link,
+        (Some(mut first), Some(mut second)) => {
+            if first.priority > second.priority {
+                first.push_down();
+                first.right = merge(first.right.take(), Some(second));
+                first.update();
+                Some(first)
+            } else {
+                second.push_down();
+                second.left = merge(Some(first), second.left.take());
+                second.update();
+                Some(second)
+            }
+        }
+    }
+}
+
+/// A sequence stored as a randomised balanced tree (a treap), where reversals
+/// are recorded on nodes and only carried out when the nodes are next visited.
+#[derive(Debug, Clone)]
+pub struct Rope<T> {
+    root: Link<T>,
+}
+
+impl<T> Default for Rope<T> {
+    fn default() -> Self {
+        Rope { root: None }
+    }
+}
+
+impl<T> Rope<T> {
+    pub fn new() -> Self {
+        Rope::default()
+    }
+
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.root = merge(self.root.take(), Some(Box::new(Node::new(value))));
+    }
+
+    /// Move everything from `other` onto the end.
+    pub fn append(&mut self, other: Rope<T>) {
+        self.root = merge(self.root.take(), other.root);
+    }
+
+    /// Split off everything from index `at` onwards, like `Vec::split_off`.
+    pub fn split_off(&mut self, at: usize) -> Rope<T> {
+        assert!(at <= self.len(), "Split index {} is past the end", at);
+        let (first, second) = split(self.root.take(), at);
+        self.root = first;
+        Rope { root: second }
+    }
+
+    pub fn reverse(&mut self) {
+        if let Some(root) = &mut self.root {
+            root.reversed = !root.reversed;
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let mut link = &self.root;
+        let mut index = index;
+        // Whether reversals above the current node have flipped it.
+        let mut flipped = false;
+        while let Some(node) = link {
+            flipped ^= node.reversed;
+            let (before, after) = if flipped {
+                (&node.right, &node.left)
+            } else {
+                (&node.left, &node.right)
+            };
+            let before_size = size(before);
+            if index < before_size {
+                link = before;
+            } else if index == before_size {
+                return Some(&node.value);
+            } else {
+                index -= before_size + 1;
+                link = after;
+            }
+        }
+        None
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.len().checked_sub(1).and_then(|i| self.get(i))
+    }
+
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        fn visit<T: Clone>(link: &Link<T>, flipped: bool, out: &mut Vec<T>) {
+            if let Some(node) = link {
+                let flipped = flipped ^ node.reversed;
+                let (before, after) = if flipped {
+                    (&node.right, &node.left)
+                } else {
+                    (&node.left, &node.right)
+                };
+                visit(before, flipped, out);
+                out.push(node.value.clone());
+                visit(after, flipped, out);
+            }
+        }
+
+        let mut out = Vec::with_capacity(self.len());
+        visit(&self.root, false, &mut out);
+        out
+    }
+}
+
+impl<T> FromIterator<T> for Rope<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut rope = Rope::new();
+        for value in iter {
+            rope.push(value);
+        }
+        rope
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rope_operations() {
+        let mut rope: Rope<u32> = (0..10).collect();
+        assert_eq!(rope.len(), 10);
+        assert_eq!(rope.get(3), Some(&3));
+
+        let mut tail = rope.split_off(6);
+        assert_eq!(rope.to_vec(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(tail.to_vec(), vec![6, 7, 8, 9]);
+
+        tail.reverse();
+        assert_eq!(tail.last(), Some(&6));
+        let mut middle = rope.split_off(2);
+        middle.reverse();
+        tail.append(middle);
+        assert_eq!(tail.to_vec(), vec![9, 8, 7, 6, 5, 4, 3, 2]);
+
+        tail.reverse();
+        let end = tail.split_off(3);
+        assert_eq!(tail.to_vec(), vec![2, 3, 4]);
+        assert_eq!(end.to_vec(), vec![5, 6, 7, 8, 9]);
+        assert_eq!(
+            (0..5).map(|i| *end.get(i).unwrap()).collect::<Vec<_>>(),
+            end.to_vec()
+        );
+
+        let all = rope.split_off(0);
+        assert_eq!(all.to_vec(), vec![0, 1]);
+        assert!(rope.is_empty());
+        assert_eq!(rope.last(), None);
+    }
+}